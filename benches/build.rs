@@ -0,0 +1,84 @@
+use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+
+/// A report style query with many literal fragments, added via the `impl Into<String>`
+/// setters.
+fn many_clauses() -> ComposableQueryBuilder {
+    let mut q = ComposableQueryBuilder::new()
+        .table("orders")
+        .select("orders.id")
+        .select("orders.total")
+        .select("users.email")
+        .join("inner join users on users.id = orders.user_id")
+        .join("left join refunds on refunds.order_id = orders.id")
+        .group_by("orders.id")
+        .group_by("users.email");
+
+    for i in 0..50 {
+        q = q
+            .where_clause("orders.status_id <> ?", i)
+            .or_where("orders.total > ?", i * 100);
+    }
+
+    q.order_by("orders.id", OrderDir::Asc).limit(100)
+}
+
+/// The same query via the `_static` setters, which store the literals without copying them.
+fn many_static_clauses() -> ComposableQueryBuilder {
+    let mut q = ComposableQueryBuilder::new()
+        .table("orders")
+        .select_static("orders.id")
+        .select_static("orders.total")
+        .select_static("users.email")
+        .join_static("inner join users on users.id = orders.user_id")
+        .join_static("left join refunds on refunds.order_id = orders.id")
+        .group_by_static("orders.id")
+        .group_by_static("users.email");
+
+    for i in 0..50 {
+        q = q
+            .where_static("orders.status_id <> ?", i)
+            .or_where_static("orders.total > ?", i * 100);
+    }
+
+    q.order_by("orders.id", OrderDir::Asc).limit(100)
+}
+
+fn bench_build(c: &mut Criterion) {
+    c.bench_function("build many clauses", |b| {
+        b.iter(|| black_box(many_clauses()))
+    });
+    c.bench_function("build many static clauses", |b| {
+        b.iter(|| black_box(many_static_clauses()))
+    });
+    c.bench_function("build and render many clauses", |b| {
+        b.iter(|| black_box(many_clauses().parts()))
+    });
+    c.bench_function("build and render many static clauses", |b| {
+        b.iter(|| black_box(many_static_clauses().parts()))
+    });
+}
+
+/// Many joins and where clauses, rendering only, to measure the output string's growth.
+fn bench_render(c: &mut Criterion) {
+    let mut q = ComposableQueryBuilder::new().table("events");
+    for i in 0..40 {
+        q = q.join(format!(
+            "left join event_tags t{i} on t{i}.event_id = events.id and t{i}.tag_id = {i}"
+        ));
+    }
+    for i in 0..200 {
+        q = q.where_clause("events.kind <> ?", i);
+    }
+
+    c.bench_function("render many joins and clauses", |b| {
+        b.iter_batched(
+            || q.clone(),
+            |q| black_box(q.parts()),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_build, bench_render);
+criterion_main!(benches);
@@ -0,0 +1,92 @@
+use crate::sql_value::SQLValue;
+
+/// Builds a `case when ... then ... end` expression. Conditions are raw sql, which may bind
+/// a value via a `?` placeholder, while results are always bound.
+///
+/// ```rust
+/// use composable_query_builder::{CaseBuilder, ComposableQueryBuilder};
+/// let state = CaseBuilder::new()
+///     .when("status_id = 1", "active")
+///     .when_bind("status_id = ?", 2, "pending")
+///     .else_("inactive");
+/// let query = ComposableQueryBuilder::new()
+///     .table("users")
+///     .select("id")
+///     .select_case(state, "state")
+///     .where_clause("org_id = ?", 7);
+///
+/// assert_eq!(
+///     "select id, case when status_id = 1 then $1 when status_id = $2 then $3 else $4 end as state from users where org_id = $5",
+///     query.sql()
+/// );
+/// ```
+#[derive(Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CaseBuilder {
+    /// The condition, its values, and the result.
+    whens: Vec<(String, Vec<SQLValue>, SQLValue)>,
+    else_: Option<SQLValue>,
+}
+
+impl CaseBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `when condition then result` branch. The condition is used as is.
+    pub fn when(mut self, condition: impl Into<String>, result: impl Into<SQLValue>) -> Self {
+        self.whens.push((condition.into(), vec![], result.into()));
+        self
+    }
+
+    /// Like [when](CaseBuilder::when), binding `v` to the `?` placeholder in the condition.
+    pub fn when_bind(
+        mut self,
+        condition: impl Into<String>,
+        v: impl Into<SQLValue>,
+        result: impl Into<SQLValue>,
+    ) -> Self {
+        self.whens
+            .push((condition.into(), vec![v.into()], result.into()));
+        self
+    }
+
+    /// Sets the result when no branch matches. Without it, the expression is `null`.
+    pub fn else_(mut self, result: impl Into<SQLValue>) -> Self {
+        self.else_ = Some(result.into());
+        self
+    }
+
+    /// Returns the `case ... end` expression, with `?` placeholders, along with the values to
+    /// bind.
+    pub fn parts(self) -> (String, Vec<SQLValue>) {
+        let mut out = "case".to_string();
+        let mut values = vec![];
+
+        for (condition, condition_values, result) in self.whens {
+            out.push_str(" when ");
+            out.push_str(&condition);
+            out.push_str(" then ?");
+            values.extend(condition_values);
+            values.push(result);
+        }
+
+        if let Some(result) = self.else_ {
+            out.push_str(" else ?");
+            values.push(result);
+        }
+
+        out.push_str(" end");
+
+        (out, values)
+    }
+
+    /// Like [parts](CaseBuilder::parts), aliasing the expression, ie `case ... end as alias`.
+    pub fn end_as(self, alias: impl AsRef<str>) -> (String, Vec<SQLValue>) {
+        let (mut out, values) = self.parts();
+        out.push_str(" as ");
+        out.push_str(alias.as_ref());
+
+        (out, values)
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::BuildError;
+
+/// An allowlist of column names that may be referenced dynamically, ie from user input. See
+/// [order_by_checked](crate::ComposableQueryBuilder::order_by_checked).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnSet {
+    columns: Vec<String>,
+}
+
+impl ColumnSet {
+    pub fn new(columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            columns: columns.into_iter().map(|c| c.into()).collect(),
+        }
+    }
+
+    pub fn contains(&self, column: &str) -> bool {
+        self.columns.iter().any(|c| c == column)
+    }
+
+    /// Returns the column if it's in the set, otherwise a [BuildError::UnknownColumn].
+    pub fn check<'a>(&self, column: &'a str) -> Result<&'a str, BuildError> {
+        if self.contains(column) {
+            Ok(column)
+        } else {
+            Err(BuildError::UnknownColumn {
+                column: column.to_string(),
+            })
+        }
+    }
+
+    pub fn extend(&mut self, other: ColumnSet) {
+        self.columns.extend(other.columns);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::ident;
+use crate::placeholder::PlaceholderStyle;
+
+/// The database a [ComposableQueryBuilder](crate::ComposableQueryBuilder) renders sql for.
+///
+/// The generated sql is shared between dialects, only the placeholder style, identifier
+/// quoting and the sqlx `QueryBuilder` used for binding differ.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Dialect {
+    /// Numbered `$1` placeholders, bound via `QueryBuilder<Postgres>`.
+    #[default]
+    Postgres,
+    /// Positional `?` placeholders, bound via `QueryBuilder<MySql>`.
+    MySql,
+    /// Positional `?` placeholders, bound via `QueryBuilder<Sqlite>`.
+    Sqlite,
+}
+
+impl Dialect {
+    /// The placeholder style sqlx uses for this dialect.
+    pub fn placeholder_style(&self) -> PlaceholderStyle {
+        match self {
+            Dialect::Postgres => PlaceholderStyle::Numbered,
+            Dialect::MySql | Dialect::Sqlite => PlaceholderStyle::Positional,
+        }
+    }
+
+    /// The character identifiers are quoted with, a backtick for MySQL and a double quote
+    /// otherwise.
+    fn ident_quote(&self) -> char {
+        match self {
+            Dialect::MySql => '`',
+            Dialect::Postgres | Dialect::Sqlite => '"',
+        }
+    }
+
+    /// Like [quote_ident](crate::quote_ident), quoting for this dialect.
+    ///
+    /// ```rust
+    /// use composable_query_builder::Dialect;
+    ///
+    /// assert_eq!("`order`", Dialect::MySql.quote_ident("order"));
+    /// assert_eq!("\"order\"", Dialect::Postgres.quote_ident("order"));
+    /// ```
+    pub fn quote_ident(&self, name: impl AsRef<str>) -> String {
+        ident::quote_ident_with(name.as_ref(), self.ident_quote())
+    }
+
+    /// Like [safe_ident](crate::safe_ident), quoting for this dialect.
+    pub fn safe_ident(&self, name: impl AsRef<str>) -> String {
+        ident::safe_ident_with(name.as_ref(), self.ident_quote())
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+
+use crate::dialect::Dialect;
+
+/// A mistake caught by [validate](crate::ComposableQueryBuilder::validate) before the query is
+/// run. Passing validation doesn't guarantee the sql is valid, only that none of these
+/// common mistakes were made.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// No table was set, or it was set to an empty string.
+    EmptyTable,
+    /// The number of `?` placeholders doesn't match the number of values provided.
+    PlaceholderMismatch { placeholders: usize, values: usize },
+    /// An order by column comes before the `distinct on` columns, which Postgres rejects.
+    DistinctOnOrderMismatch { column: String },
+    /// A delete without where clauses, see
+    /// [allow_delete_all](crate::ComposableQueryBuilder::allow_delete_all).
+    UnboundedDelete,
+    /// A dynamic column that isn't in the builder's [ColumnSet](crate::ColumnSet).
+    UnknownColumn { column: String },
+    /// An insert row with a different number of values than there are columns.
+    RowLengthMismatch { expected: usize, found: usize },
+    /// An insert with columns but no rows of values.
+    InsertWithoutRows,
+    /// An insert with rows of values but no columns.
+    InsertWithoutColumns,
+    /// A negative or too large value passed to
+    /// [try_limit](crate::ComposableQueryBuilder::try_limit) or
+    /// [try_offset](crate::ComposableQueryBuilder::try_offset).
+    OutOfRange { clause: String, value: String },
+    /// A table modifier, ie `only` or `tablesample`, set on a complex table or a statement that doesn't
+    /// support it.
+    UnsupportedTableModifier { modifier: String },
+    /// A `u64` value above `i64::MAX`, which Postgres and SQLite can't bind without it
+    /// wrapping to a negative number.
+    U64Overflow { value: u64 },
+    /// A single insert row, along with any params shared by every statement, binds more
+    /// than the `max_params` passed to
+    /// [into_batched_builders](crate::ComposableQueryBuilder::into_batched_builders).
+    TooManyParams { max: usize, needed: usize },
+    /// An array value bound for a dialect without an array type, ie MySQL.
+    UnsupportedArray { dialect: Dialect },
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::EmptyTable => write!(f, "no table was set"),
+            BuildError::PlaceholderMismatch {
+                placeholders,
+                values,
+            } => write!(
+                f,
+                "found {} placeholders but {} values",
+                placeholders, values
+            ),
+            BuildError::DistinctOnOrderMismatch { column } => write!(
+                f,
+                "order by column `{}` must match the leading distinct on columns",
+                column
+            ),
+            BuildError::UnboundedDelete => write!(
+                f,
+                "delete without where clauses, call allow_delete_all() to opt in"
+            ),
+            BuildError::UnknownColumn { column } => {
+                write!(f, "column `{}` is not in the allowed columns", column)
+            }
+            BuildError::RowLengthMismatch { expected, found } => write!(
+                f,
+                "insert row has {} values but there are {} columns",
+                found, expected
+            ),
+            BuildError::InsertWithoutRows => write!(f, "insert has columns but no rows"),
+            BuildError::InsertWithoutColumns => write!(f, "insert has rows but no columns"),
+            BuildError::OutOfRange { clause, value } => {
+                write!(f, "{} `{}` must be a non-negative integer", clause, value)
+            }
+            BuildError::UnsupportedTableModifier { modifier } => write!(
+                f,
+                "`{}` is not supported for this table or statement",
+                modifier
+            ),
+            BuildError::U64Overflow { value } => {
+                write!(f, "u64 value `{}` is too large to bind as an i64", value)
+            }
+            BuildError::TooManyParams { max, needed } => write!(
+                f,
+                "a single insert row binds {} params but the limit is {}",
+                needed, max
+            ),
+            BuildError::UnsupportedArray { dialect } => {
+                write!(f, "array values are not supported by {:?}", dialect)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
@@ -0,0 +1,142 @@
+//! Helpers that build and run a query in one go, enabled by the `fetch` feature.
+
+use sqlx::postgres::PgRow;
+use sqlx::{Executor, FromRow, Postgres, QueryBuilder};
+
+use crate::ComposableQueryBuilder;
+
+impl ComposableQueryBuilder {
+    /// Builds the query via [try_into_builder](ComposableQueryBuilder::try_into_builder), so a
+    /// bad value returns an error rather than panicking. sqlx 0.7 has no error for values that
+    /// can't be encoded, so the [BuildError](crate::BuildError) is returned boxed in
+    /// [sqlx::Error::Configuration].
+    fn fetch_builder(&self) -> Result<QueryBuilder<'static, Postgres>, sqlx::Error> {
+        self.clone()
+            .try_into_builder()
+            .map_err(|e| sqlx::Error::Configuration(Box::new(e)))
+    }
+
+    /// Builds the query and fetches every row, mapped via [FromRow]. Shorthand for
+    /// `try_into_builder()?.build_query_as::<T>().fetch_all(executor)`.
+    ///
+    /// ```rust,no_run
+    /// use composable_query_builder::ComposableQueryBuilder;
+    ///
+    /// #[derive(sqlx::FromRow)]
+    /// struct User {
+    ///     id: i64,
+    ///     email: String,
+    /// }
+    ///
+    /// async fn active_users(pool: &sqlx::PgPool) -> Result<Vec<User>, sqlx::Error> {
+    ///     ComposableQueryBuilder::new()
+    ///         .table("users")
+    ///         .select_many(["id", "email"])
+    ///         .where_clause("status_id = ?", 1)
+    ///         .fetch_all(pool)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn fetch_all<'c, T, E>(&self, executor: E) -> Result<Vec<T>, sqlx::Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut qb = self.fetch_builder()?;
+        qb.build_query_as::<T>().fetch_all(executor).await
+    }
+
+    /// Builds the query and fetches exactly one row, erroring with
+    /// [sqlx::Error::RowNotFound] if there isn't one.
+    ///
+    /// ```rust,no_run
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    ///
+    /// #[derive(sqlx::FromRow)]
+    /// struct Order {
+    ///     id: i64,
+    ///     total: i64,
+    /// }
+    ///
+    /// async fn latest_order(pool: &sqlx::PgPool, user_id: i64) -> Result<Order, sqlx::Error> {
+    ///     ComposableQueryBuilder::new()
+    ///         .table("orders")
+    ///         .select_many(["id", "total"])
+    ///         .where_clause("user_id = ?", user_id)
+    ///         .first("created_at", OrderDir::Desc)
+    ///         .fetch_one(pool)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn fetch_one<'c, T, E>(&self, executor: E) -> Result<T, sqlx::Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut qb = self.fetch_builder()?;
+        qb.build_query_as::<T>().fetch_one(executor).await
+    }
+
+    /// Builds the query and fetches at most one row.
+    pub async fn fetch_optional<'c, T, E>(&self, executor: E) -> Result<Option<T>, sqlx::Error>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut qb = self.fetch_builder()?;
+        qb.build_query_as::<T>().fetch_optional(executor).await
+    }
+
+    /// Builds the query and fetches the first column of exactly one row, such as the result of
+    /// a [count](ComposableQueryBuilder::count) query.
+    ///
+    /// ```rust,no_run
+    /// use composable_query_builder::ComposableQueryBuilder;
+    ///
+    /// async fn active_user_count(pool: &sqlx::PgPool) -> Result<i64, sqlx::Error> {
+    ///     ComposableQueryBuilder::new()
+    ///         .table("users")
+    ///         .where_clause("status_id = ?", 1)
+    ///         .count()
+    ///         .fetch_scalar(pool)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn fetch_scalar<'c, T, E>(&self, executor: E) -> Result<T, sqlx::Error>
+    where
+        T: Send + Unpin,
+        (T,): for<'r> FromRow<'r, PgRow>,
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut qb = self.fetch_builder()?;
+        qb.build_query_scalar::<T>().fetch_one(executor).await
+    }
+
+    /// Fetches the query plan as json, running the query with
+    /// [explain](ComposableQueryBuilder::explain) unless
+    /// [explain_analyze](ComposableQueryBuilder::explain_analyze) was already called.
+    ///
+    /// ```rust,no_run
+    /// use composable_query_builder::ComposableQueryBuilder;
+    ///
+    /// async fn plan(pool: &sqlx::PgPool) -> Result<serde_json::Value, sqlx::Error> {
+    ///     ComposableQueryBuilder::new()
+    ///         .table("users")
+    ///         .where_clause("email = ?", "bob@example.com")
+    ///         .fetch_plan(pool)
+    ///         .await
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    pub async fn fetch_plan<'c, E>(&self, executor: E) -> Result<serde_json::Value, sqlx::Error>
+    where
+        E: Executor<'c, Database = Postgres>,
+    {
+        let mut query = self.clone();
+        if query.explain.is_none() {
+            query = query.explain();
+        }
+
+        query.fetch_scalar(executor).await
+    }
+}
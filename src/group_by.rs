@@ -0,0 +1,47 @@
+use std::borrow::Cow;
+
+use itertools::Itertools;
+
+/// A single entry in a group by clause.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupBy {
+    /// A plain grouping column or expression.
+    Column(Cow<'static, str>),
+    /// `rollup (a, b)`, grouping by each prefix of the columns plus a grand total.
+    Rollup(Vec<String>),
+    /// `cube (a, b)`, grouping by every combination of the columns.
+    Cube(Vec<String>),
+    /// `grouping sets ((a, b), (a), ())`, where an empty set is the grand total.
+    Sets(Vec<Vec<String>>),
+}
+
+impl GroupBy {
+    pub fn sql(&self) -> Cow<'_, str> {
+        match self {
+            GroupBy::Column(s) => Cow::Borrowed(s),
+            GroupBy::Rollup(columns) => format!("rollup ({})", columns.join(", ")).into(),
+            GroupBy::Cube(columns) => format!("cube ({})", columns.join(", ")).into(),
+            GroupBy::Sets(sets) => format!(
+                "grouping sets ({})",
+                sets.iter()
+                    .map(|set| format!("({})", set.join(", ")))
+                    .join(", ")
+            )
+            .into(),
+        }
+    }
+
+    /// A rough estimate of the rendered length, used to pre-size the output.
+    pub fn len_hint(&self) -> usize {
+        match self {
+            GroupBy::Column(s) => s.len(),
+            GroupBy::Rollup(columns) | GroupBy::Cube(columns) => {
+                columns.iter().map(|c| c.len() + 2).sum::<usize>() + 10
+            }
+            GroupBy::Sets(sets) => {
+                sets.iter().flatten().map(|c| c.len() + 2).sum::<usize>() + sets.len() * 4 + 16
+            }
+        }
+    }
+}
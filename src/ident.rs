@@ -0,0 +1,58 @@
+/// Wraps an identifier in double quotes, doubling any embedded quotes, so it can be safely
+/// interpolated as a table or column name. A `?` is escaped as `??` so it isn't read as a
+/// placeholder, and `:name` inside the quotes is never read as a named param. Qualified names
+/// should be quoted per part, ie
+/// `format!("{}.{}", quote_ident(table), quote_ident(column))`. Use
+/// [Dialect::quote_ident](crate::Dialect::quote_ident) for MySQL's backticks.
+///
+/// ```rust
+/// use composable_query_builder::quote_ident;
+///
+/// assert_eq!("\"email\"", quote_ident("email"));
+/// assert_eq!("\"a\"\"; drop table users; --\"", quote_ident("a\"; drop table users; --"));
+/// assert_eq!("\"a??b\"", quote_ident("a?b"));
+/// ```
+pub fn quote_ident(name: impl AsRef<str>) -> String {
+    quote_ident_with(name.as_ref(), '"')
+}
+
+/// Wraps an identifier in `quote`, doubling any embedded `quote`s and `?`s.
+pub(crate) fn quote_ident_with(name: &str, quote: char) -> String {
+    let escaped = name
+        .replace(quote, &quote.to_string().repeat(2))
+        .replace('?', "??");
+    format!("{quote}{escaped}{quote}")
+}
+
+/// Returns the identifier as is when it's a plain `[a-zA-Z_][a-zA-Z0-9_]*` name, and
+/// [quote_ident]s it otherwise. Useful for accepting column names from user input.
+///
+/// ```rust
+/// use composable_query_builder::safe_ident;
+///
+/// assert_eq!("created_at", safe_ident("created_at"));
+/// assert_eq!("\"id; drop table users\"", safe_ident("id; drop table users"));
+/// ```
+pub fn safe_ident(name: impl AsRef<str>) -> String {
+    safe_ident_with(name.as_ref(), '"')
+}
+
+/// Like [safe_ident], quoting with `quote`.
+pub(crate) fn safe_ident_with(name: &str, quote: char) -> String {
+    if is_plain_ident(name) {
+        name.to_string()
+    } else {
+        quote_ident_with(name, quote)
+    }
+}
+
+/// Whether `name` matches `^[a-zA-Z_][a-zA-Z0-9_]*$`.
+pub fn is_plain_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
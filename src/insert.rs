@@ -0,0 +1,190 @@
+use itertools::Itertools;
+
+use crate::error::BuildError;
+use crate::sql_value::SQLValue;
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InsertValues {
+    columns: Vec<String>,
+    /// One entry per row, each with a value per column.
+    rows: Vec<Vec<SQLValue>>,
+}
+
+impl InsertValues {
+    pub fn new() -> Self {
+        Self {
+            columns: vec![],
+            rows: vec![],
+        }
+    }
+
+    /// Adds a column along with its value in the first row.
+    pub fn push(&mut self, column: impl Into<String>, value: impl Into<SQLValue>) {
+        self.columns.push(column.into());
+        match self.rows.first_mut() {
+            Some(row) => row.push(value.into()),
+            None => self.rows.push(vec![value.into()]),
+        }
+    }
+
+    /// Replaces the columns and rows.
+    pub fn set_rows(&mut self, columns: Vec<String>, rows: Vec<Vec<SQLValue>>) {
+        self.columns = columns;
+        self.rows = rows;
+    }
+
+    /// Sets the columns without adding any values.
+    pub fn set_columns(&mut self, columns: Vec<String>) {
+        self.columns = columns;
+    }
+
+    /// Adds rows, each with a value per column.
+    pub fn push_rows(&mut self, rows: impl IntoIterator<Item = Vec<SQLValue>>) {
+        self.rows.extend(rows);
+    }
+
+    /// Extends the columns, and each row with the matching row from `other`.
+    pub fn extend(&mut self, other: InsertValues) {
+        self.columns.extend(other.columns);
+        for (i, row) in other.rows.into_iter().enumerate() {
+            match self.rows.get_mut(i) {
+                Some(existing) => existing.extend(row),
+                None => self.rows.push(row),
+            }
+        }
+    }
+
+    /// Splits the rows into chunks, each with the same columns, so that no chunk binds more
+    /// than `max_params` values along with the `shared` params repeated in every statement.
+    /// Errors if a single row can't fit.
+    pub fn chunks(self, max_params: usize, shared: usize) -> Result<Vec<InsertValues>, BuildError> {
+        let per_row = self.columns.len().max(1);
+        let rows_per_chunk = max_params.saturating_sub(shared) / per_row;
+        if rows_per_chunk == 0 && !self.rows.is_empty() {
+            return Err(BuildError::TooManyParams {
+                max: max_params,
+                needed: shared + per_row,
+            });
+        }
+
+        let columns = self.columns;
+        let mut out = vec![];
+        let mut rows = self.rows.into_iter().peekable();
+
+        while rows.peek().is_some() {
+            out.push(InsertValues {
+                columns: columns.clone(),
+                rows: rows.by_ref().take(rows_per_chunk).collect(),
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Errors if any row doesn't have a value for every column, or if there are columns
+    /// without rows or rows without columns.
+    pub fn check_rows(&self) -> Result<(), BuildError> {
+        match (self.columns.is_empty(), self.rows.is_empty()) {
+            (false, true) => return Err(BuildError::InsertWithoutRows),
+            (true, false) => return Err(BuildError::InsertWithoutColumns),
+            _ => {}
+        }
+
+        match self.rows.iter().find(|row| row.len() != self.columns.len()) {
+            Some(row) => Err(BuildError::RowLengthMismatch {
+                expected: self.columns.len(),
+                found: row.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// The number of values that will be bound.
+    pub fn param_count(&self) -> usize {
+        self.rows.iter().flatten().filter(|v| v.is_bound()).count()
+    }
+
+    /// Returns the column list and values portion of an insert statement, with a
+    /// parenthesized list of placeholders per row. When no values have been pushed, this
+    /// falls back to `default values` so the statement is still valid.
+    pub fn parts(self) -> (String, Vec<SQLValue>) {
+        if self.columns.is_empty() {
+            return (" default values".to_string(), vec![]);
+        }
+
+        let mut out = " (".to_string();
+        out.push_str(&self.columns.join(", "));
+        out.push_str(") values ");
+        out.push_str(
+            &self
+                .rows
+                .iter()
+                .map(|row| format!("({})", vec!["?"; row.len()].join(", ")))
+                .join(", "),
+        );
+
+        (out, self.rows.into_iter().flatten().collect())
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum ConflictAction {
+    Nothing,
+    Update(Vec<(String, String)>),
+}
+
+/// An `on conflict` clause for an insert statement.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OnConflict {
+    target: Vec<String>,
+    action: ConflictAction,
+}
+
+impl OnConflict {
+    pub fn new(target: Vec<String>) -> Self {
+        Self {
+            target,
+            action: ConflictAction::Nothing,
+        }
+    }
+
+    pub fn do_nothing(&mut self) {
+        self.action = ConflictAction::Nothing;
+    }
+
+    pub fn do_update_set(&mut self, column: impl Into<String>, expr: impl Into<String>) {
+        let assignment = (column.into(), expr.into());
+        match &mut self.action {
+            ConflictAction::Nothing => self.action = ConflictAction::Update(vec![assignment]),
+            ConflictAction::Update(assignments) => assignments.push(assignment),
+        }
+    }
+
+    pub fn sql(&self) -> String {
+        let mut out = " on conflict".to_string();
+
+        if !self.target.is_empty() {
+            out.push_str(" (");
+            out.push_str(&self.target.join(", "));
+            out.push(')');
+        }
+
+        match &self.action {
+            ConflictAction::Nothing => out.push_str(" do nothing"),
+            ConflictAction::Update(assignments) => {
+                out.push_str(" do update set ");
+                out.push_str(
+                    &assignments
+                        .iter()
+                        .map(|(column, expr)| format!("{} = {}", column, expr))
+                        .join(", "),
+                );
+            }
+        }
+
+        out
+    }
+}
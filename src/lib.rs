@@ -59,55 +59,347 @@
 //! let sql = query.sql();
 //! assert_eq!("select * from users where id = $1 and status_id = $2", sql);
 //! ```
+mod case;
+mod column_set;
+mod dialect;
+mod error;
+#[cfg(feature = "fetch")]
+mod fetch;
+mod group_by;
+mod ident;
+mod insert;
 mod order;
+mod placeholder;
+mod set_clause;
 mod sql_value;
+mod table_sample;
 mod where_clause;
 
+use std::borrow::Cow;
+use std::fmt;
+
 use itertools::{EitherOrBoth, Itertools};
+#[cfg(feature = "mysql")]
+use sqlx::MySql;
+#[cfg(feature = "sqlite")]
+use sqlx::Sqlite;
 use sqlx::{Postgres, QueryBuilder};
 
-use crate::sql_value::SQLValue;
+use crate::group_by::GroupBy;
+use crate::insert::{InsertValues, OnConflict};
+use crate::placeholder::Segment;
+use crate::set_clause::SetClauses;
+use crate::table_sample::TableSample;
 use crate::where_clause::WhereClauses;
-pub use order::OrderDir;
+pub use case::CaseBuilder;
+pub use column_set::ColumnSet;
+pub use dialect::Dialect;
+pub use error::BuildError;
+pub use ident::{is_plain_ident, quote_ident, safe_ident};
+pub use order::{NullsOrder, OrderDir, ParseOrderDirError};
+pub use placeholder::PlaceholderStyle;
+pub use sql_value::SQLValue;
+pub use table_sample::SampleMethod;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TableType {
     Simple(String),
     Complex(String, Vec<ComposableQueryBuilder>),
 }
 
-#[derive(Clone)]
+impl TableType {
+    fn param_count(&self) -> usize {
+        match self {
+            TableType::Simple(_) => 0,
+            TableType::Complex(_, parts) => parts.iter().map(|qb| qb.value_count()).sum(),
+        }
+    }
+
+    fn parts(self) -> (String, Vec<SQLValue>) {
+        let mut str = String::new();
+        let mut vals = vec![];
+
+        match self {
+            TableType::Simple(s) => str.push_str(&s),
+            TableType::Complex(s, parts) => {
+                // Escaped `??` are kept as is, they're unescaped once the whole query is built
+                let table_parts = placeholder::split(&s, false);
+
+                for pair in table_parts.into_iter().zip_longest(parts) {
+                    match pair {
+                        EitherOrBoth::Both(table_part, qb) => {
+                            str.extend(table_part);
+                            let (s, parts) = qb.parts();
+                            str.push_str(s.as_str());
+                            vals.extend(parts);
+                        }
+                        EitherOrBoth::Left(table_part) => {
+                            str.extend(table_part);
+                        }
+                        EitherOrBoth::Right(qb) => {
+                            let (s, parts) = qb.parts();
+                            str.push_str(s.as_str());
+                            vals.extend(parts);
+                        }
+                    }
+                }
+            }
+        }
+
+        (str, vals)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Distinct {
+    /// `select distinct`
+    Rows,
+    /// `select distinct on (...)`
+    On(Vec<String>),
+}
+
+/// The kind of statement a [ComposableQueryBuilder] produces.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueryType {
+    Select,
+    Insert,
+    Update,
+    Delete,
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ComposableQueryBuilder {
+    dialect: Dialect,
+    query_type: QueryType,
     table: TableType,
-    select: Vec<String>,
-    group_by: Vec<String>,
-    joins: Vec<String>,
+    /// Emits `only` before a simple table, excluding inheriting tables and partitions.
+    only: bool,
+    tablesample: Option<TableSample>,
+    select: Vec<Cow<'static, str>>,
+    /// Values bound by `?` placeholders in the select list, in order.
+    select_values: Vec<SQLValue>,
+    distinct: Option<Distinct>,
+    group_by: Vec<GroupBy>,
+    joins: Vec<Cow<'static, str>>,
+    /// Values bound by `?` placeholders in the joins, in order.
+    join_values: Vec<SQLValue>,
     where_clause: WhereClauses,
+    having: WhereClauses,
     limit: Option<u64>,
     offset: Option<u64>,
-    order_by: Option<(String, OrderDir)>,
+    order_by: Vec<(String, OrderDir, Option<NullsOrder>)>,
+    insert_values: InsertValues,
+    set: SetClauses,
+    allow_delete_all: bool,
+    returning: Vec<String>,
+    on_conflict: Option<OnConflict>,
+    ctes: Vec<(String, ComposableQueryBuilder)>,
+    recursive: bool,
+    /// `union` or `union all`, along with the query being combined.
+    unions: Vec<(String, ComposableQueryBuilder)>,
+    /// `for update` or `for share`
+    lock: Option<String>,
+    /// `skip locked` or `nowait`
+    lock_wait: Option<String>,
+    /// `explain (format json)` or `explain (analyze, format json)`
+    explain: Option<String>,
+    /// Raw fragments appended to the end of the statement, along with their values.
+    raw: Vec<String>,
+    raw_values: Vec<SQLValue>,
+    /// Columns that may be referenced via the `_checked` helpers.
+    allowed_columns: ColumnSet,
+    /// Named params, referenced as `:name` and bound once.
+    params: Vec<(String, SQLValue)>,
+}
+
+impl Default for ComposableQueryBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the query with `?` placeholders, as returned by
+/// [parts](ComposableQueryBuilder::parts). Values are not inlined.
+impl fmt::Display for ComposableQueryBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.clone().parts().0)
+    }
 }
 
 impl ComposableQueryBuilder {
     pub fn new() -> Self {
         Self {
+            dialect: Dialect::Postgres,
+            query_type: QueryType::Select,
             table: TableType::Simple(String::new()),
+            only: false,
+            tablesample: None,
             select: vec![],
+            select_values: vec![],
+            distinct: None,
             group_by: vec![],
             joins: vec![],
+            join_values: vec![],
             where_clause: WhereClauses::new(),
+            having: WhereClauses::new(),
             limit: None,
             offset: None,
-            order_by: None,
+            order_by: vec![],
+            insert_values: InsertValues::new(),
+            set: SetClauses::new(),
+            allow_delete_all: false,
+            returning: vec![],
+            on_conflict: None,
+            ctes: vec![],
+            recursive: false,
+            unions: vec![],
+            lock: None,
+            lock_wait: None,
+            explain: None,
+            raw: vec![],
+            raw_values: vec![],
+            allowed_columns: ColumnSet::default(),
+            params: vec![],
         }
     }
 
+    /// Like [new](ComposableQueryBuilder::new), but pre-allocates room for the given number of
+    /// where clauses, joins and select columns. Useful when building from a long list of
+    /// filters, to avoid growing the clause list one push at a time.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let ids = 1..=100;
+    /// let query = ids.fold(
+    ///     ComposableQueryBuilder::with_capacity(100, 0, 0).table("users"),
+    ///     |q, id| q.or_where("id = ?", id),
+    /// );
+    ///
+    /// assert_eq!(100, query.param_count());
+    /// ```
+    pub fn with_capacity(clauses: usize, joins: usize, selects: usize) -> Self {
+        let mut qb = Self::new();
+        qb.where_clause = WhereClauses::with_capacity(clauses);
+        qb.joins = Vec::with_capacity(joins);
+        qb.select = Vec::with_capacity(selects);
+        qb
+    }
+
+    /// Starts an insert statement into the given table. Columns and values are added via
+    /// [value](ComposableQueryBuilder::value) and [values](ComposableQueryBuilder::values).
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::insert_into("users")
+    ///     .value("name", "Bob".to_string())
+    ///     .value("status_id", 1)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("insert into users (name, status_id) values ($1, $2)", sql);
+    /// ```
+    ///
+    /// If no values are provided, the statement falls back to `insert into users default values`.
+    pub fn insert_into(table: impl Into<String>) -> Self {
+        let mut qb = Self::new().table(table);
+        qb.query_type = QueryType::Insert;
+        qb
+    }
+
+    /// Sets the dialect the query is rendered for. Defaults to [Dialect::Postgres].
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, Dialect};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .dialect(Dialect::MySql)
+    ///     .table("users")
+    ///     .where_clause("id = ?", 1);
+    ///
+    /// assert_eq!("select * from users where id = ?", query.sql());
+    /// ```
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
     /// Sets the table name for the query.
     pub fn table(mut self, table: impl Into<String>) -> Self {
         self.table = TableType::Simple(table.into());
         self
     }
 
+    /// Sets an aliased table for the query, ie `users as u`, so columns can be referenced as
+    /// `u.id` in joins and self joins.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table_as("users", "u")
+    ///     .select("u.id")
+    ///     .left_join("users as m", "m.id = u.manager_id")
+    ///     .where_clause("m.status_id = ?", 1);
+    ///
+    /// assert_eq!(
+    ///     "select u.id from users as u left join users as m on m.id = u.manager_id where m.status_id = $1",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn table_as(self, table: impl AsRef<str>, alias: impl AsRef<str>) -> Self {
+        self.table(format!("{} as {}", table.as_ref(), alias.as_ref()))
+    }
+
+    /// Sets a schema qualified table for the query, ie `analytics.events`. Each part is
+    /// passed through [safe_ident], so it's only quoted when it isn't a plain identifier, and
+    /// quoted for the builder's [dialect](ComposableQueryBuilder::dialect).
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table_in_schema("analytics", "events")
+    ///     .where_clause("user_id = ?", 1);
+    ///
+    /// assert_eq!("select * from analytics.events where user_id = $1", query.sql());
+    /// ```
+    pub fn table_in_schema(self, schema: impl AsRef<str>, table: impl AsRef<str>) -> Self {
+        let table = self.schema_table(schema, table);
+        self.table(table)
+    }
+
+    /// Like [table_in_schema](ComposableQueryBuilder::table_in_schema), with an alias as in
+    /// [table_as](ComposableQueryBuilder::table_as).
+    pub fn table_in_schema_as(
+        self,
+        schema: impl AsRef<str>,
+        table: impl AsRef<str>,
+        alias: impl AsRef<str>,
+    ) -> Self {
+        let table = self.schema_table(schema, table);
+        self.table_as(table, alias)
+    }
+
+    /// Sets a table built from a template, where each `?` is replaced by the sql of the
+    /// matching sub-builder. The sub-builders' values are bound in order, ahead of the outer
+    /// query's join and where values.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let recent = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .where_not_null("shipped_at")
+    ///     .where_clause("total > ?", 100);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .complex_table("(?) as recent", vec![recent])
+    ///     .where_clause("recent.user_id = ?", 1);
+    ///
+    /// assert_eq!(
+    ///     "select * from (select * from orders where shipped_at is not null and total > $1) as recent where recent.user_id = $2",
+    ///     query.sql()
+    /// );
+    /// ```
     pub fn complex_table(
         mut self,
         complex_table: impl Into<String>,
@@ -117,348 +409,5427 @@ impl ComposableQueryBuilder {
         self
     }
 
-    /// Adds a single column to the select clause.
-    pub fn select(mut self, select: impl Into<String>) -> Self {
-        self.select.push(select.into());
+    /// Reads only the named table, not the tables inheriting from it or its partitions, ie
+    /// `from only parent`. Applies to selects, updates and deletes on a simple table;
+    /// [validate](ComposableQueryBuilder::validate) errors for complex tables and inserts,
+    /// where it's otherwise ignored.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("measurements")
+    ///     .only()
+    ///     .where_clause("city_id = ?", 1);
+    ///
+    /// assert_eq!("select * from only measurements where city_id = $1", query.sql());
+    /// ```
+    pub fn only(mut self) -> Self {
+        self.only = true;
         self
     }
 
-    /// Adds multiple columns to the select clause.
-    pub fn select_many(mut self, select: impl IntoIterator<Item = impl Into<String>>) -> Self {
-        self.select.extend(select.into_iter().map(|s| s.into()));
+    /// Reads a random sample of roughly `percent` of the table, ie
+    /// `from events tablesample system (10)`, for fast approximate aggregates. Applies to
+    /// selects on a simple table; [validate](ComposableQueryBuilder::validate) errors
+    /// otherwise.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, SampleMethod};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("events")
+    ///     .select("count(*) * 10")
+    ///     .tablesample(SampleMethod::System, 10.0);
+    ///
+    /// assert_eq!(
+    ///     "select count(*) * 10 from events tablesample system ($1::real)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn tablesample(mut self, method: SampleMethod, percent: f64) -> Self {
+        self.tablesample = Some(TableSample::new(method, percent, None));
         self
     }
 
-    /// Adds a single group by clause
-    pub fn group_by(mut self, group_by: impl Into<String>) -> Self {
-        self.group_by.push(group_by.into());
+    /// Like [tablesample](ComposableQueryBuilder::tablesample), adding `repeatable (seed)` so
+    /// the same seed returns the same sample while the table is unchanged.
+    pub fn tablesample_repeatable(mut self, method: SampleMethod, percent: f64, seed: i64) -> Self {
+        self.tablesample = Some(TableSample::new(method, percent, Some(seed)));
         self
     }
 
-    /// Adds multiple group by clause
-    pub fn group_by_many(mut self, group_by: impl IntoIterator<Item = impl Into<String>>) -> Self {
-        self.group_by.extend(group_by.into_iter().map(|s| s.into()));
-        self
+    /// Selects from a derived table, ie `from (select ...) as alias`, binding the
+    /// subquery's values ahead of the outer query's.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let totals = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select_many(["user_id", "sum(total) as total"])
+    ///     .where_clause("status_id = ?", 1)
+    ///     .group_by("user_id");
+    /// let query = ComposableQueryBuilder::new()
+    ///     .from_subquery(totals, "t")
+    ///     .where_clause("t.total > ?", 100);
+    ///
+    /// assert_eq!(
+    ///     "select * from (select user_id, sum(total) as total from orders where status_id = $1 group by user_id) as t where t.total > $2",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn from_subquery(self, query: ComposableQueryBuilder, alias: impl AsRef<str>) -> Self {
+        self.complex_table(format!("(?) as {}", alias.as_ref()), vec![query])
     }
 
-    /// Adds a single join clause
+    /// Starts an update statement against the given table. Assignments are added via
+    /// [set](ComposableQueryBuilder::set) and rows are filtered with the usual where clauses.
+    ///
     /// ```rust
     /// use composable_query_builder::ComposableQueryBuilder;
-    /// let query = ComposableQueryBuilder::new()
-    ///    .table("users")
-    ///    .join("left join subscriptions on subscriptions.user_id = users.id")
-    ///    .into_builder();
+    /// let query = ComposableQueryBuilder::update("users")
+    ///     .set("status_id", 2)
+    ///     .where_clause("id = ?", 1)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("update users set status_id = $1 where id = $2", sql);
+    /// ```
+    pub fn update(table: impl Into<String>) -> Self {
+        let mut qb = Self::new().table(table);
+        qb.query_type = QueryType::Update;
+        qb
+    }
+
+    /// Starts a delete statement against the given table.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::delete_from("users")
+    ///     .where_clause("id = ?", 1)
+    ///     .into_builder();
+    ///
     /// let sql = query.sql();
+    /// assert_eq!("delete from users where id = $1", sql);
+    /// ```
     ///
-    /// assert_eq!("select * from users left join subscriptions on subscriptions.user_id = users.id", sql);
-    pub fn join(mut self, join: impl Into<String>) -> Self {
-        self.joins.push(join.into());
+    /// To guard against accidentally deleting every row, a delete without any where clauses
+    /// is rejected by [validate](ComposableQueryBuilder::validate) and
+    /// [try_into_builder](ComposableQueryBuilder::try_into_builder), and makes
+    /// [into_builder](ComposableQueryBuilder::into_builder) panic, unless
+    /// [allow_delete_all](ComposableQueryBuilder::allow_delete_all) was called.
+    pub fn delete_from(table: impl Into<String>) -> Self {
+        let mut qb = Self::new().table(table);
+        qb.query_type = QueryType::Delete;
+        qb
+    }
+
+    /// Opts in to building a delete statement with no where clauses, ie `delete from users`.
+    pub fn allow_delete_all(mut self) -> Self {
+        self.allow_delete_all = true;
         self
     }
 
-    /// Adds a single where clause. Values are expected to be denoted via a `?` placeholder.
+    /// Adds a common table expression, ie `with name as (...)`, ahead of the query. Multiple
+    /// ctes are emitted in the order they were added, and their values are bound before the
+    /// main query's values.
     ///
     /// ```rust
     /// use composable_query_builder::ComposableQueryBuilder;
+    /// let active_users = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 1);
     /// let query = ComposableQueryBuilder::new()
-    ///   .table("users")
-    ///   .where_clause("id = ?", 1);
-    /// let sql = query.sql();
+    ///     .with("active_users", active_users)
+    ///     .table("active_users")
+    ///     .where_clause("org_id = ?", 2);
     ///
-    /// assert_eq!("select * from users where id = $1", sql);
+    /// assert_eq!(
+    ///     "with active_users as (select * from users where status_id = $1) select * from active_users where org_id = $2",
+    ///     query.sql()
+    /// );
     /// ```
-    pub fn where_clause(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
-        self.where_clause
-            .push(where_clause.into(), v, BoolKind::And);
+    pub fn with(mut self, name: impl Into<String>, query: ComposableQueryBuilder) -> Self {
+        self.ctes.push((name.into(), query));
         self
     }
 
-    pub fn or_where(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
-        self.where_clause.push(where_clause.into(), v, BoolKind::Or);
+    /// Adds a common table expression and marks the with clause as `with recursive`. The name
+    /// may include a column list, ie `t(n)`.
+    pub fn with_recursive(
+        mut self,
+        name: impl Into<String>,
+        query: ComposableQueryBuilder,
+    ) -> Self {
+        self.recursive = true;
+        self.with(name, query)
+    }
+
+    /// Combines this select with another using `union`, removing duplicate rows.
+    ///
+    /// The order by, limit and offset of this builder apply to the combined result. If `other`
+    /// has its own order by, limit, offset, ctes or unions, it's wrapped in parentheses so they
+    /// only apply to that half.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let admins = ComposableQueryBuilder::new()
+    ///     .table("admins")
+    ///     .select("email")
+    ///     .where_clause("org_id = ?", 2);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select("email")
+    ///     .where_clause("org_id = ?", 1)
+    ///     .union(admins)
+    ///     .order_by("email", OrderDir::Asc);
+    ///
+    /// assert_eq!(
+    ///     "select email from users where org_id = $1 union select email from admins where org_id = $2 order by email asc",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn union(mut self, other: ComposableQueryBuilder) -> Self {
+        self.unions.push(("union".to_string(), other));
         self
     }
 
-    pub fn multi_where(mut self, where_clause: impl Into<String>, v: Vec<SQLValue>) -> Self {
-        self.where_clause.push_multi(where_clause.into(), v);
+    /// Combines this select with another using `union all`, keeping duplicate rows. See
+    /// [union](ComposableQueryBuilder::union).
+    pub fn union_all(mut self, other: ComposableQueryBuilder) -> Self {
+        self.unions.push(("union all".to_string(), other));
         self
     }
 
-    /// Conditionally add a [where_clause](ComposableQueryBuilder::where_clause). The given
-    /// callback is lazily evaluated, so it's only called if the condition is true.
-    pub fn where_if(mut self, condition: bool, cb: impl Fn() -> (String, SQLValue)) -> Self {
-        if !condition {
-            return self;
-        }
+    /// Locks the selected rows with `for update`. Locking clauses only apply to selects and
+    /// are emitted at the very end of the query.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("jobs")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .for_update()
+    ///     .skip_locked();
+    ///
+    /// assert_eq!(
+    ///     "select * from jobs where status_id = $1 for update skip locked",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn for_update(mut self) -> Self {
+        self.lock = Some("for update".to_string());
+        self
+    }
 
-        let (s, v) = cb();
-        self.where_clause.push(s, v, BoolKind::And);
+    /// Locks the selected rows with `for share`. See
+    /// [for_update](ComposableQueryBuilder::for_update).
+    pub fn for_share(mut self) -> Self {
+        self.lock = Some("for share".to_string());
+        self
+    }
 
+    /// Skips rows that are already locked instead of waiting for them. Only applies along with
+    /// [for_update](ComposableQueryBuilder::for_update) or
+    /// [for_share](ComposableQueryBuilder::for_share).
+    pub fn skip_locked(mut self) -> Self {
+        self.lock_wait = Some("skip locked".to_string());
         self
     }
 
-    pub fn limit(mut self, limit: u64) -> Self {
-        self.limit = Some(limit);
+    /// Errors instead of waiting when a row is already locked. Only applies along with
+    /// [for_update](ComposableQueryBuilder::for_update) or
+    /// [for_share](ComposableQueryBuilder::for_share).
+    pub fn no_wait(mut self) -> Self {
+        self.lock_wait = Some("nowait".to_string());
         self
     }
 
-    pub fn limit_opt(mut self, limit: Option<u64>) -> Self {
-        match limit {
-            Some(limit) => self.limit = Some(limit),
-            None => self.limit = None,
-        }
+    /// Prefixes the statement with `explain (format json)`, so running it returns the query
+    /// plan as a single json value instead of rows.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("email = ?", "bob@example.com")
+    ///     .explain();
+    ///
+    /// assert_eq!(
+    ///     "explain (format json) select * from users where email = $1",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn explain(mut self) -> Self {
+        self.explain = Some("explain (format json)".to_string());
         self
     }
 
-    pub fn offset(mut self, offset: u64) -> Self {
-        self.offset = Some(offset);
+    /// Like [explain](ComposableQueryBuilder::explain), but uses `explain (analyze, format
+    /// json)` to include actual timings. The statement is executed, so an analyzed insert,
+    /// update or delete changes data unless it's run in a transaction that's rolled back.
+    pub fn explain_analyze(mut self) -> Self {
+        self.explain = Some("explain (analyze, format json)".to_string());
         self
     }
 
-    pub fn offset_opt(mut self, offset: Option<u64>) -> Self {
-        match offset {
-            Some(offset) => self.offset = Some(offset),
-            None => self.offset = None,
-        }
+    /// Adds a single column to the select clause.
+    pub fn select(mut self, select: impl Into<String>) -> Self {
+        self.select.push(Cow::Owned(select.into()));
         self
     }
 
-    pub fn order_by(mut self, col: impl ToString, dir: OrderDir) -> Self {
-        self.order_by = Some((col.to_string(), dir));
+    /// Like [select](ComposableQueryBuilder::select), but stores a `'static` literal without
+    /// copying it onto the heap.
+    pub fn select_static(mut self, select: &'static str) -> Self {
+        self.select.push(Cow::Borrowed(select));
         self
     }
 
-    pub fn parts(self) -> (String, Vec<SQLValue>) {
-        let mut vals = vec![];
+    /// Adds an `expr as alias` column to the select clause.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("user_id")
+    ///     .select_as("count(*)", "total")
+    ///     .group_by("user_id");
+    ///
+    /// assert_eq!(
+    ///     "select user_id, count(*) as total from orders group by user_id",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn select_as(self, expr: impl AsRef<str>, alias: impl AsRef<str>) -> Self {
+        self.select(format!("{} as {}", expr.as_ref(), alias.as_ref()))
+    }
 
-        let mut str = "select ".to_string();
-        // let mut str = "select\n    ".to_string();
+    /// Adds a `coalesce(expr, default) as alias` column to the select clause. Both sides are
+    /// raw sql, so the default can be a literal or another expression.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("user_id")
+    ///     .select_coalesce("sum(total)", "0", "total")
+    ///     .group_by("user_id");
+    ///
+    /// assert_eq!(
+    ///     "select user_id, coalesce(sum(total), 0) as total from orders group by user_id",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn select_coalesce(
+        self,
+        expr: impl AsRef<str>,
+        default: impl AsRef<str>,
+        alias: impl AsRef<str>,
+    ) -> Self {
+        self.select_as(
+            format!("coalesce({}, {})", expr.as_ref(), default.as_ref()),
+            alias,
+        )
+    }
 
-        if self.select.is_empty() {
-            str.push('*');
-        } else {
-            str.push_str(&self.select.join(", "));
-        }
-        str.push_str(" from ");
-        // str.push_str("\nfrom ");
+    /// Adds a `nullif(expr, value) as alias` column to the select clause, which is `null`
+    /// when `expr` equals `value`. Handy for avoiding division by zero.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("campaigns")
+    ///     .select_nullif("clicks", "0", "clicks");
+    ///
+    /// assert_eq!("select nullif(clicks, 0) as clicks from campaigns", query.sql());
+    /// ```
+    pub fn select_nullif(
+        self,
+        expr: impl AsRef<str>,
+        value: impl AsRef<str>,
+        alias: impl AsRef<str>,
+    ) -> Self {
+        self.select_as(
+            format!("nullif({}, {})", expr.as_ref(), value.as_ref()),
+            alias,
+        )
+    }
 
-        match self.table {
-            TableType::Simple(s) => str.push_str(&s),
-            TableType::Complex(s, parts) => {
-                let table_parts = s.split('?');
+    /// Adds multiple columns to the select clause.
+    pub fn select_many(mut self, select: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.select
+            .extend(select.into_iter().map(|s| Cow::Owned(s.into())));
+        self
+    }
 
-                for pair in table_parts.zip_longest(parts) {
-                    match pair {
-                        EitherOrBoth::Both(table_part, qb) => {
-                            str.push_str(table_part);
-                            let (s, parts) = qb.parts();
-                            str.push_str(s.as_str());
-                            vals.extend(parts);
-                        }
-                        EitherOrBoth::Left(table_part) => {
-                            str.push_str(table_part);
-                        }
-                        EitherOrBoth::Right(qb) => {
-                            let (s, parts) = qb.parts();
-                            str.push_str(s.as_str());
-                            vals.extend(parts);
-                        }
-                    }
-                }
-            }
+    /// Selects every column from a single table, ie `users.*`, which avoids ambiguous
+    /// columns when joining. Composes with the other select helpers.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select_table_star("users")
+    ///     .select("orders.id")
+    ///     .inner_join("orders", "orders.user_id = users.id");
+    ///
+    /// assert_eq!(
+    ///     "select users.*, orders.id from users inner join orders on orders.user_id = users.id",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn select_table_star(self, table: impl AsRef<str>) -> Self {
+        self.select(format!("{}.*", table.as_ref()))
+    }
+
+    /// Conditionally add a [select](ComposableQueryBuilder::select) column. The given callback
+    /// is lazily evaluated, so it's only called if the condition is true.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let include_orders = false;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select("id")
+    ///     .select_if(include_orders, || {
+    ///         "(select count(*) from orders where orders.user_id = users.id) as order_count"
+    ///     });
+    ///
+    /// assert_eq!("select id from users", query.sql());
+    /// ```
+    pub fn select_if<S: Into<String>>(self, condition: bool, cb: impl Fn() -> S) -> Self {
+        if !condition {
+            return self;
+        }
+
+        self.select(cb())
+    }
+
+    /// Adds a window function column to the select clause, assembling the `over (...)` clause
+    /// from its partition and order by columns. Either may be empty.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("id")
+    ///     .window_select(
+    ///         "rn",
+    ///         "row_number()",
+    ///         ["user_id"],
+    ///         [("created_at", OrderDir::Desc)],
+    ///     );
+    ///
+    /// assert_eq!(
+    ///     "select id, row_number() over (partition by user_id order by created_at desc) as rn from orders",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn window_select(
+        self,
+        alias: impl AsRef<str>,
+        func: impl AsRef<str>,
+        partition_by: impl IntoIterator<Item = impl Into<String>>,
+        order_by: impl IntoIterator<Item = (impl ToString, OrderDir)>,
+    ) -> Self {
+        let partition_by = partition_by.into_iter().map(|s| s.into()).join(", ");
+        let order_by = order_by
+            .into_iter()
+            .map(|(col, dir)| format!("{} {}", col.to_string(), dir.as_str()))
+            .join(", ");
+
+        let mut over = vec![];
+        if !partition_by.is_empty() {
+            over.push(format!("partition by {}", partition_by));
+        }
+        if !order_by.is_empty() {
+            over.push(format!("order by {}", order_by));
+        }
+
+        self.select_as(
+            format!("{} over ({})", func.as_ref(), over.join(" ")),
+            alias,
+        )
+    }
+
+    /// Keeps the first `n` rows of each group, ie "the 3 most recent orders per user". The
+    /// query is numbered with `row_number()` as `rn` and wrapped in an outer query filtering
+    /// on `rn <= n`, which is returned for further filtering or ordering. The outer query
+    /// selects from the `ranked` alias and includes the `rn` column.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .top_n_per_group(["user_id"], [("created_at", OrderDir::Desc)], 3);
+    ///
+    /// assert_eq!(
+    ///     "select * from (select *, row_number() over (partition by user_id order by created_at desc) as rn from orders where status_id = $1) as ranked where ranked.rn <= $2",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn top_n_per_group(
+        mut self,
+        partition_by: impl IntoIterator<Item = impl Into<String>>,
+        order_by: impl IntoIterator<Item = (impl ToString, OrderDir)>,
+        n: u64,
+    ) -> ComposableQueryBuilder {
+        if self.select.is_empty() {
+            self = self.select("*");
+        }
+
+        let dialect = self.dialect;
+        let ranked = self.window_select("rn", "row_number()", partition_by, order_by);
+
+        ComposableQueryBuilder::new()
+            .dialect(dialect)
+            .from_subquery(ranked, "ranked")
+            .where_clause("ranked.rn <= ?", n)
+    }
+
+    /// Adds a raw expression to the select clause, for things the builder doesn't model such
+    /// as window functions. This is an escape hatch: the expression is used as is, so any
+    /// values must be denoted via `?` placeholders and passed in `values`, never formatted in.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("id")
+    ///     .raw_select("total > ? as is_large", vec![100.into()])
+    ///     .where_clause("user_id = ?", 1);
+    ///
+    /// assert_eq!(
+    ///     "select id, total > $1 as is_large from orders where user_id = $2",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn raw_select(mut self, expr: impl Into<String>, values: Vec<SQLValue>) -> Self {
+        self.select.push(Cow::Owned(expr.into()));
+        self.select_values.extend(values);
+        self
+    }
+
+    /// Adds a [CaseBuilder] expression to the select clause as `alias`, binding its values in
+    /// place.
+    pub fn select_case(self, case: CaseBuilder, alias: impl AsRef<str>) -> Self {
+        let (expr, values) = case.end_as(alias);
+        self.raw_select(expr, values)
+    }
+
+    /// Appends a raw fragment to the very end of the statement, after everything the builder
+    /// renders itself. This is an escape hatch: the fragment is used as is, so any values must
+    /// be denoted via `?` placeholders and passed in `values`, never formatted in.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("id = ?", 1)
+    ///     .append_raw("fetch first ? rows only", vec![5.into()]);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where id = $1 fetch first $2 rows only",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn append_raw(mut self, fragment: impl Into<String>, values: Vec<SQLValue>) -> Self {
+        self.raw.push(fragment.into());
+        self.raw_values.extend(values);
+        self
+    }
+
+    /// Merges another builder into this one, so a base query and a set of filters can be built
+    /// separately and then combined.
+    ///
+    /// - ctes, selects, joins, group bys, order bys, unions and returning columns from `other`
+    ///   are appended
+    /// - where and having clauses are combined with `and`, wrapping each non-empty side in
+    ///   parentheses
+    /// - set and insert values from `other` are appended
+    /// - the table, distinct, limit, offset and on conflict clause from `other` win when set
+    /// - the statement type of `self` is kept
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let base = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .join("left join orders on orders.user_id = users.id")
+    ///     .where_clause("users.org_id = ?", 1);
+    /// let filters = ComposableQueryBuilder::new()
+    ///     .or_where("orders.total > ?", 100)
+    ///     .or_where("orders.total < ?", 10);
+    ///
+    /// let query = base.merge(filters).into_builder();
+    /// let sql = query.sql();
+    /// assert_eq!(
+    ///     "select * from users left join orders on orders.user_id = users.id where (users.org_id = $1) and (orders.total > $2 or orders.total < $3)",
+    ///     sql
+    /// );
+    /// ```
+    pub fn merge(mut self, other: ComposableQueryBuilder) -> Self {
+        let table_is_set = match &other.table {
+            TableType::Simple(s) => !s.is_empty(),
+            TableType::Complex(_, _) => true,
+        };
+        if table_is_set {
+            self.table = other.table;
+            self.only = other.only;
+            self.tablesample = other.tablesample;
+        }
+
+        self.select.extend(other.select);
+        self.select_values.extend(other.select_values);
+        self.joins.extend(other.joins);
+        self.join_values.extend(other.join_values);
+        self.group_by.extend(other.group_by);
+        self.order_by.extend(other.order_by);
+        self.returning.extend(other.returning);
+
+        self.where_clause = self.where_clause.and(other.where_clause);
+        self.having = self.having.and(other.having);
+
+        self.insert_values.extend(other.insert_values);
+        self.set.extend(other.set);
+        self.allow_delete_all |= other.allow_delete_all;
+
+        self.distinct = other.distinct.or(self.distinct);
+        self.limit = other.limit.or(self.limit);
+        self.offset = other.offset.or(self.offset);
+        self.on_conflict = other.on_conflict.or(self.on_conflict);
+
+        self.ctes.extend(other.ctes);
+        self.recursive |= other.recursive;
+        self.unions.extend(other.unions);
+        self.raw.extend(other.raw);
+        self.raw_values.extend(other.raw_values);
+        self.allowed_columns.extend(other.allowed_columns);
+        self.params.extend(other.params);
+
+        self
+    }
+
+    /// Turns the query into a `count(*)` query, replacing the select list and dropping any
+    /// order by, limit and offset. Where clauses and joins are kept. A query using distinct,
+    /// group by, having or unions is counted from a subquery instead, ie
+    /// `select count(*) from (...) as t`, so it still returns a single count of its rows.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select("email")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .order_by("email", OrderDir::Asc)
+    ///     .limit(10)
+    ///     .count()
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select count(*) from users where status_id = $1", sql);
+    /// ```
+    pub fn count(self) -> Self {
+        self.count_select("count(*)")
+    }
+
+    /// Like [count](ComposableQueryBuilder::count), but counts the distinct values of `col`.
+    /// When the query is counted from a subquery, `col` must be in its select list.
+    pub fn count_distinct(self, col: impl AsRef<str>) -> Self {
+        self.count_select(format!("count(distinct {})", col.as_ref()))
+    }
+
+    /// Returns a [count](ComposableQueryBuilder::count) version of this query, leaving this
+    /// one untouched. Handy for deriving a total from a paginated query.
+    pub fn count_query(&self) -> Self {
+        self.clone().count()
+    }
+
+    fn count_select(mut self, select: impl Into<Cow<'static, str>>) -> Self {
+        self.order_by = vec![];
+        self.limit = None;
+        self.offset = None;
+
+        let changes_rows = self.distinct.is_some()
+            || !self.group_by.is_empty()
+            || !self.having.is_empty()
+            || !self.unions.is_empty();
+        if !changes_rows {
+            self.select = vec![select.into()];
+            self.select_values = vec![];
+            return self;
+        }
+
+        // Named params and explain only apply to the outermost query
+        let params = std::mem::take(&mut self.params);
+        let explain = self.explain.take();
+        let mut outer = ComposableQueryBuilder::new()
+            .dialect(self.dialect)
+            .from_subquery(self, "t");
+        outer.select = vec![select.into()];
+        outer.params = params;
+        outer.explain = explain;
+        outer
+    }
+
+    /// Removes all where clauses.
+    pub fn clear_where(mut self) -> Self {
+        self.where_clause = WhereClauses::new();
+        self
+    }
+
+    /// Removes all order by columns.
+    pub fn clear_order_by(mut self) -> Self {
+        self.order_by = vec![];
+        self
+    }
+
+    /// Removes the limit.
+    pub fn clear_limit(mut self) -> Self {
+        self.limit = None;
+        self
+    }
+
+    /// Removes all selected columns, falling back to `select *`.
+    pub fn clear_select(mut self) -> Self {
+        self.select = vec![];
+        self.select_values = vec![];
+        self
+    }
+
+    /// Removes all joins.
+    pub fn clear_joins(mut self) -> Self {
+        self.joins = vec![];
+        self.join_values = vec![];
+        self
+    }
+
+    /// Makes the select return only distinct rows, ie `select distinct`.
+    pub fn distinct(mut self) -> Self {
+        self.distinct = Some(Distinct::Rows);
+        self
+    }
+
+    /// Makes the select return only the first row of each set of rows where the given
+    /// expressions are equal, ie `select distinct on (...)`.
+    ///
+    /// Postgres requires the leading [order_by](ComposableQueryBuilder::order_by) columns to
+    /// match the `distinct on` expressions, which is left up to the caller.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select_many(["user_id", "total"])
+    ///     .distinct_on(["user_id"])
+    ///     .order_by("user_id", OrderDir::Asc)
+    ///     .order_by("created_at", OrderDir::Desc)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!(
+    ///     "select distinct on (user_id) user_id, total from orders order by user_id asc, created_at desc",
+    ///     sql
+    /// );
+    /// ```
+    pub fn distinct_on(mut self, on: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.distinct = Some(Distinct::On(on.into_iter().map(|s| s.into()).collect()));
+        self
+    }
+
+    /// Adds a single group by clause
+    pub fn group_by(mut self, group_by: impl Into<String>) -> Self {
+        self.group_by
+            .push(GroupBy::Column(Cow::Owned(group_by.into())));
+        self
+    }
+
+    /// Like [group_by](ComposableQueryBuilder::group_by), but stores a `'static` literal
+    /// without copying it onto the heap.
+    pub fn group_by_static(mut self, group_by: &'static str) -> Self {
+        self.group_by.push(GroupBy::Column(Cow::Borrowed(group_by)));
+        self
+    }
+
+    /// Adds multiple group by clause
+    pub fn group_by_many(mut self, group_by: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.group_by.extend(
+            group_by
+                .into_iter()
+                .map(|s| GroupBy::Column(Cow::Owned(s.into()))),
+        );
+        self
+    }
+
+    /// Groups by `rollup (...)`, producing a row per prefix of the columns along with
+    /// subtotals and a grand total. Composes with plain [group_by](ComposableQueryBuilder::group_by)
+    /// columns.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("sales")
+    ///     .select_many(["region", "city", "sum(total)"])
+    ///     .group_by_rollup(["region", "city"]);
+    ///
+    /// assert_eq!(
+    ///     "select region, city, sum(total) from sales group by rollup (region, city)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn group_by_rollup(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.group_by.push(GroupBy::Rollup(
+            columns.into_iter().map(|c| c.into()).collect(),
+        ));
+        self
+    }
+
+    /// Groups by `grouping sets (...)`, producing a row per set in a single query. An empty
+    /// set groups every row together, ie the grand total.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("sales")
+    ///     .select_many(["region", "city", "sum(total)"])
+    ///     .grouping_sets(vec![vec!["region", "city"], vec!["region"], vec![]]);
+    ///
+    /// assert_eq!(
+    ///     "select region, city, sum(total) from sales group by grouping sets ((region, city), (region), ())",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn grouping_sets(
+        mut self,
+        sets: impl IntoIterator<Item = impl IntoIterator<Item = impl Into<String>>>,
+    ) -> Self {
+        self.group_by.push(GroupBy::Sets(
+            sets.into_iter()
+                .map(|set| set.into_iter().map(|c| c.into()).collect())
+                .collect(),
+        ));
+        self
+    }
+
+    /// Groups by `cube (...)`, producing a row for every combination of the columns. See
+    /// [group_by_rollup](ComposableQueryBuilder::group_by_rollup).
+    pub fn group_by_cube(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.group_by.push(GroupBy::Cube(
+            columns.into_iter().map(|c| c.into()).collect(),
+        ));
+        self
+    }
+
+    /// Conditionally add a [group_by](ComposableQueryBuilder::group_by) column. The given
+    /// callback is lazily evaluated, so it's only called if the condition is true.
+    pub fn group_by_if<S: Into<String>>(self, condition: bool, cb: impl Fn() -> S) -> Self {
+        if !condition {
+            return self;
+        }
+
+        self.group_by(cb())
+    }
+
+    /// Adds a single join clause
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///    .table("users")
+    ///    .join("left join subscriptions on subscriptions.user_id = users.id")
+    ///    .into_builder();
+    /// let sql = query.sql();
+    ///
+    /// assert_eq!("select * from users left join subscriptions on subscriptions.user_id = users.id", sql);
+    pub fn join(mut self, join: impl Into<String>) -> Self {
+        self.joins.push(Cow::Owned(join.into()));
+        self
+    }
+
+    /// Like [join](ComposableQueryBuilder::join), but stores a `'static` literal without
+    /// copying it onto the heap.
+    pub fn join_static(mut self, join: &'static str) -> Self {
+        self.joins.push(Cow::Borrowed(join));
+        self
+    }
+
+    /// Conditionally add a [join](ComposableQueryBuilder::join). The given callback is lazily
+    /// evaluated, so it's only called if the condition is true.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let min_total: Option<i64> = Some(100);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .join_if(min_total.is_some(), || "left join orders on orders.user_id = users.id")
+    ///     .where_if(min_total.is_some(), || ("orders.total >= ?".to_string(), min_total.into()));
+    ///
+    /// assert_eq!(
+    ///     "select * from users left join orders on orders.user_id = users.id where orders.total >= $1",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn join_if<S: Into<String>>(self, condition: bool, cb: impl Fn() -> S) -> Self {
+        if !condition {
+            return self;
         }
 
-        // Joins
-        for j in self.joins {
-            str.push(' ');
-            // str.push('\n');
-            str.push_str(&j);
-        }
+        self.join(cb())
+    }
+
+    /// Adds a single join clause whose condition binds values via `?` placeholders.
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///    .table("users")
+    ///    .join_with(
+    ///        "left join orders on orders.user_id = users.id and orders.status_id = ?",
+    ///        vec![2.into()],
+    ///    )
+    ///    .where_clause("users.id = ?", 1);
+    ///
+    /// assert_eq!(
+    ///     "select * from users left join orders on orders.user_id = users.id and orders.status_id = $1 where users.id = $2",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn join_with(mut self, join: impl Into<String>, values: Vec<SQLValue>) -> Self {
+        self.joins.push(Cow::Owned(join.into()));
+        self.join_values.extend(values);
+        self
+    }
+
+    /// Adds a `cross join lateral (select ...) as alias` clause. The subquery can reference
+    /// columns from earlier tables, and its values are bound in place along with the other
+    /// join values.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let latest_order = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("total")
+    ///     .where_clause("orders.user_id = users.id and orders.status_id = ?", 2)
+    ///     .order_by("created_at", OrderDir::Desc)
+    ///     .limit(1);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select_many(["users.id", "latest.total"])
+    ///     .lateral_join(latest_order, "latest")
+    ///     .where_clause("users.org_id = ?", 1);
+    ///
+    /// assert_eq!(
+    ///     "select users.id, latest.total from users cross join lateral (select total from orders where orders.user_id = users.id and orders.status_id = $1 order by created_at desc limit $2) as latest where users.org_id = $3",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn lateral_join(self, query: ComposableQueryBuilder, alias: impl AsRef<str>) -> Self {
+        let (sql, values) = query.parts();
+        self.join_with(
+            format!("cross join lateral ({}) as {}", sql, alias.as_ref()),
+            values,
+        )
+    }
+
+    /// Adds an `inner join table on ...` clause
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///    .table("users")
+    ///    .inner_join("subscriptions", "subscriptions.user_id = users.id");
+    ///
+    /// assert_eq!("select * from users inner join subscriptions on subscriptions.user_id = users.id", query.sql());
+    /// ```
+    pub fn inner_join(self, table: impl AsRef<str>, on: impl AsRef<str>) -> Self {
+        self.join(format!("inner join {} on {}", table.as_ref(), on.as_ref()))
+    }
+
+    /// Adds a `left join table on ...` clause
+    pub fn left_join(self, table: impl AsRef<str>, on: impl AsRef<str>) -> Self {
+        self.join(format!("left join {} on {}", table.as_ref(), on.as_ref()))
+    }
+
+    /// Adds a `right join table on ...` clause
+    pub fn right_join(self, table: impl AsRef<str>, on: impl AsRef<str>) -> Self {
+        self.join(format!("right join {} on {}", table.as_ref(), on.as_ref()))
+    }
+
+    /// Adds a `cross join table` clause
+    pub fn cross_join(self, table: impl AsRef<str>) -> Self {
+        self.join(format!("cross join {}", table.as_ref()))
+    }
+
+    /// Adds a single where clause. Values are expected to be denoted via a `?` placeholder.
+    /// A literal `?`, such as the jsonb `?` operator, is written as `??`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///   .table("users")
+    ///   .where_clause("id = ?", 1);
+    /// let sql = query.sql();
+    ///
+    /// assert_eq!("select * from users where id = $1", sql);
+    /// ```
+    pub fn where_clause(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.where_clause
+            .push(where_clause.into(), v, BoolKind::And);
+        self
+    }
+
+    /// Like [where_clause](ComposableQueryBuilder::where_clause), but stores a `'static`
+    /// literal without copying it onto the heap.
+    pub fn where_static(mut self, where_clause: &'static str, v: impl Into<SQLValue>) -> Self {
+        self.where_clause.push(where_clause, v, BoolKind::And);
+        self
+    }
+
+    /// Adds a [where_clause](ComposableQueryBuilder::where_clause) only when the value is
+    /// `Some`, binding the inner value. `None` adds nothing, rather than comparing to `null`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let status_id: Option<i64> = Some(2);
+    /// let org_id: Option<i64> = None;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause_opt("status_id = ?", status_id)
+    ///     .where_clause_opt("org_id = ?", org_id);
+    ///
+    /// assert_eq!("select * from users where status_id = $1", query.sql());
+    /// ```
+    pub fn where_clause_opt(
+        self,
+        where_clause: impl Into<String>,
+        v: Option<impl Into<SQLValue>>,
+    ) -> Self {
+        match v {
+            Some(v) => self.where_clause(where_clause, v),
+            None => self,
+        }
+    }
+
+    /// Adds a where clause joined to the previous clause with `or`. Clauses aren't grouped,
+    /// so `and` binds tighter as usual; use
+    /// [where_group](ComposableQueryBuilder::where_group) to control precedence.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .or_where("status_id = ?", 2);
+    ///
+    /// assert_eq!("select * from users where status_id = $1 or status_id = $2", query.sql());
+    /// ```
+    pub fn or_where(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.where_clause.push(where_clause.into(), v, BoolKind::Or);
+        self
+    }
+
+    /// Like [or_where](ComposableQueryBuilder::or_where), but stores a `'static` literal
+    /// without copying it onto the heap.
+    pub fn or_where_static(mut self, where_clause: &'static str, v: impl Into<SQLValue>) -> Self {
+        self.where_clause.push(where_clause, v, BoolKind::Or);
+        self
+    }
+
+    /// Adds a where clause joined by an explicit [BoolKind], for building filters from data
+    /// without branching between [where_clause](ComposableQueryBuilder::where_clause) and
+    /// [or_where](ComposableQueryBuilder::or_where).
+    ///
+    /// ```rust
+    /// use composable_query_builder::{BoolKind, ComposableQueryBuilder};
+    /// let filters = [("status_id = ?", 1), ("status_id = ?", 2)];
+    /// let query = filters
+    ///     .into_iter()
+    ///     .fold(ComposableQueryBuilder::new().table("users"), |q, (clause, v)| {
+    ///         q.where_with(clause, v, BoolKind::Or)
+    ///     });
+    ///
+    /// assert_eq!("select * from users where status_id = $1 or status_id = $2", query.sql());
+    /// ```
+    pub fn where_with(
+        mut self,
+        where_clause: impl Into<String>,
+        v: impl Into<SQLValue>,
+        kind: BoolKind,
+    ) -> Self {
+        self.where_clause.push(where_clause.into(), v, kind);
+        self
+    }
+
+    /// Adds a where clause ahead of every existing one, such as a tenant scope that should
+    /// read first. It's always joined with `and`, and the existing clauses are wrapped in
+    /// parentheses so an `or` among them can't loosen the new one.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 2)
+    ///     .where_first("org_id = ?", 1);
+    ///
+    /// assert_eq!("select * from users where org_id = $1 and (status_id = $2)", query.sql());
+    /// ```
+    pub fn where_first(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        let mut first = WhereClauses::new();
+        first.push(where_clause.into(), v, BoolKind::And);
+        first.push_group(self.where_clause, BoolKind::And);
+        self.where_clause = first;
+        self
+    }
+
+    /// Adds a where clause that binds no `?` values, such as one referencing named
+    /// [param](ComposableQueryBuilder::param)s.
+    pub fn where_raw(mut self, where_clause: impl Into<String>) -> Self {
+        self.where_clause
+            .push_many(where_clause.into(), vec![], BoolKind::And);
+        self
+    }
+
+    /// Like [where_raw](ComposableQueryBuilder::where_raw), joined with `or`.
+    pub fn or_where_raw(mut self, where_clause: impl Into<String>) -> Self {
+        self.where_clause
+            .push_many(where_clause.into(), vec![], BoolKind::Or);
+        self
+    }
+
+    /// Adds a negated where clause, ie `not (clause)`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_not("status_id = ?", 1);
+    ///
+    /// assert_eq!("select * from users where not (status_id = $1)", query.sql());
+    /// ```
+    pub fn where_not(mut self, where_clause: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.where_clause
+            .push(format!("not ({})", where_clause.into()), v, BoolKind::And);
+        self
+    }
+
+    pub fn multi_where(mut self, where_clause: impl Into<String>, v: Vec<SQLValue>) -> Self {
+        self.where_clause
+            .push_many(where_clause.into(), v, BoolKind::And);
+        self
+    }
+
+    pub fn or_multi_where(mut self, where_clause: impl Into<String>, v: Vec<SQLValue>) -> Self {
+        self.where_clause
+            .push_many(where_clause.into(), v, BoolKind::Or);
+        self
+    }
+
+    /// Adds a `column like ?` where clause. The pattern is bound as a string, so it may contain
+    /// the usual `%` and `_` wildcards.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_like("email", "%@example.com")
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users where email like $1", sql);
+    /// ```
+    pub fn where_like(mut self, column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.where_clause.push(
+            format!("{} like ?", column.into()),
+            pattern.into(),
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Adds a case-insensitive `column ilike ?` where clause.
+    pub fn where_ilike(mut self, column: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.where_clause.push(
+            format!("{} ilike ?", column.into()),
+            pattern.into(),
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Adds a `column like ? escape '\'` where clause that matches any value containing `term`.
+    /// Any `%`, `_` or `\` in the term are escaped, so they're matched literally. SQLite has no
+    /// default escape character, so it's always given explicitly. MySQL reads the backslash as
+    /// a string escape, so set the [dialect](ComposableQueryBuilder::dialect) first.
+    pub fn where_contains(mut self, column: impl Into<String>, term: impl AsRef<str>) -> Self {
+        let term = term
+            .as_ref()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        let escape = match self.dialect {
+            Dialect::MySql => "'\\\\'",
+            Dialect::Postgres | Dialect::Sqlite => "'\\'",
+        };
+        self.where_clause.push(
+            format!("{} like ? escape {}", column.into(), escape),
+            format!("%{}%", term),
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Adds a parenthesized group of where clauses. The callback receives an empty builder,
+    /// and any where clauses added to it are nested inside the group. Everything else on the
+    /// callback's builder is ignored.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_group(|g| g.or_where("status_id = ?", 1).or_where("status_id = ?", 2))
+    ///     .where_clause("email = ?", "bob@example.com".to_string())
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!(
+    ///     "select * from users where (status_id = $1 or status_id = $2) and email = $3",
+    ///     sql
+    /// );
+    /// ```
+    pub fn where_group(
+        mut self,
+        cb: impl FnOnce(ComposableQueryBuilder) -> ComposableQueryBuilder,
+    ) -> Self {
+        let group = cb(ComposableQueryBuilder::new());
+        self.where_clause
+            .push_group(group.where_clause, BoolKind::And);
+        self
+    }
+
+    /// Adds a parenthesized group of where clauses, joined with `or`. See
+    /// [where_group](ComposableQueryBuilder::where_group).
+    pub fn or_where_group(
+        mut self,
+        cb: impl FnOnce(ComposableQueryBuilder) -> ComposableQueryBuilder,
+    ) -> Self {
+        let group = cb(ComposableQueryBuilder::new());
+        self.where_clause
+            .push_group(group.where_clause, BoolKind::Or);
+        self
+    }
+
+    /// Adds a parenthesized group of `(clause, value)` pairs joined with `or`, ie
+    /// `(c1 or c2 or c3)`. The group itself is joined to the other where clauses with `and`,
+    /// and an empty list adds nothing.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("org_id = ?", 1)
+    ///     .where_any([("status_id = ?", 2), ("status_id = ?", 3)]);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where org_id = $1 and (status_id = $2 or status_id = $3)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn where_any(
+        self,
+        clauses: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+    ) -> Self {
+        self.where_clause_group(clauses, BoolKind::Or)
+    }
+
+    /// Adds each `(clause, value)` pair as its own [where_clause](ComposableQueryBuilder::where_clause),
+    /// joined with `and`. Unlike [where_all](ComposableQueryBuilder::where_all), the clauses
+    /// aren't wrapped in parentheses.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, SQLValue};
+    /// let filters: Vec<(String, SQLValue)> = vec![
+    ///     ("status_id = ?".to_string(), 2.into()),
+    ///     ("email like ?".to_string(), "%@example.com".into()),
+    /// ];
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_each(filters);
+    ///
+    /// assert_eq!("select * from users where status_id = $1 and email like $2", query.sql());
+    /// ```
+    pub fn where_each(
+        mut self,
+        clauses: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+    ) -> Self {
+        for (clause, v) in clauses {
+            self.where_clause.push(clause.into(), v, BoolKind::And);
+        }
+        self
+    }
+
+    /// Like [where_any](ComposableQueryBuilder::where_any), but the pairs are joined with
+    /// `and`, ie `(c1 and c2 and c3)`.
+    pub fn where_all(
+        self,
+        clauses: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+    ) -> Self {
+        self.where_clause_group(clauses, BoolKind::And)
+    }
+
+    fn where_clause_group(
+        mut self,
+        clauses: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+        kind: BoolKind,
+    ) -> Self {
+        let mut group = WhereClauses::new();
+        for (clause, v) in clauses {
+            group.push(clause.into(), v, kind);
+        }
+
+        self.where_clause.push_group(group, BoolKind::And);
+        self
+    }
+
+    /// Adds a `column in (?, ?, ...)` where clause, with one placeholder per value.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_in("status_id", vec![1, 2, 3])
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users where status_id in ($1, $2, $3)", sql);
+    /// ```
+    ///
+    /// An empty list can never match, so it produces `1 = 0` rather than the invalid `in ()`.
+    pub fn where_in(
+        mut self,
+        column: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<SQLValue>>,
+    ) -> Self {
+        let values: Vec<SQLValue> = values.into_iter().map(|v| v.into()).collect();
+        let clause = if values.is_empty() {
+            "1 = 0".to_string()
+        } else {
+            in_clause(column.into(), "in", values.len())
+        };
+        self.where_clause.push_many(clause, values, BoolKind::And);
+        self
+    }
+
+    /// Adds a `column not in (?, ?, ...)` where clause, with one placeholder per value.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_not_in("status_id", vec![1, 2])
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users where status_id not in ($1, $2)", sql);
+    /// ```
+    ///
+    /// An empty list excludes nothing, so it produces `1 = 1` rather than the invalid
+    /// `not in ()`.
+    pub fn where_not_in(
+        mut self,
+        column: impl Into<String>,
+        values: impl IntoIterator<Item = impl Into<SQLValue>>,
+    ) -> Self {
+        let values: Vec<SQLValue> = values.into_iter().map(|v| v.into()).collect();
+        let clause = if values.is_empty() {
+            "1 = 1".to_string()
+        } else {
+            in_clause(column.into(), "not in", values.len())
+        };
+        self.where_clause.push_many(clause, values, BoolKind::And);
+        self
+    }
+
+    /// Adds a `column in (select ...)` where clause, binding the subquery's values in place.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let big_spenders = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("user_id")
+    ///     .where_clause("total > ?", 100);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .where_in_subquery("id", big_spenders);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where status_id = $1 and id in (select user_id from orders where total > $2)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn where_in_subquery(
+        mut self,
+        column: impl AsRef<str>,
+        query: ComposableQueryBuilder,
+    ) -> Self {
+        let (s, values) = query.parts();
+        self.where_clause.push_many(
+            format!("{} in ({})", column.as_ref(), s),
+            values,
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Adds an `exists (select ...)` where clause, binding the subquery's values in place. The
+    /// subquery can reference the outer query's tables.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let big_orders = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select("1")
+    ///     .where_clause("orders.user_id = users.id and orders.total > ?", 100);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .where_exists(big_orders);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where status_id = $1 and exists (select 1 from orders where orders.user_id = users.id and orders.total > $2)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn where_exists(self, query: ComposableQueryBuilder) -> Self {
+        self.where_subquery("exists", query)
+    }
+
+    /// Adds a `not exists (select ...)` where clause. See
+    /// [where_exists](ComposableQueryBuilder::where_exists).
+    pub fn where_not_exists(self, query: ComposableQueryBuilder) -> Self {
+        self.where_subquery("not exists", query)
+    }
+
+    fn where_subquery(mut self, op: &str, query: ComposableQueryBuilder) -> Self {
+        let (s, values) = query.parts();
+        self.where_clause
+            .push_many(format!("{} ({})", op, s), values, BoolKind::And);
+        self
+    }
+
+    /// Adds a `column between ? and ?` where clause, binding `low` then `high`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .where_between("total", 10, 100)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from orders where total between $1 and $2", sql);
+    /// ```
+    pub fn where_between(
+        mut self,
+        column: impl Into<String>,
+        low: impl Into<SQLValue>,
+        high: impl Into<SQLValue>,
+    ) -> Self {
+        self.where_clause.push_many(
+            format!("{} between ? and ?", column.into()),
+            vec![low.into(), high.into()],
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Adds a `column is null` where clause. No values are bound.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_null("deleted_at")
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users where deleted_at is null", sql);
+    /// ```
+    pub fn where_null(mut self, column: impl Into<String>) -> Self {
+        self.where_clause
+            .push_many(format!("{} is null", column.into()), vec![], BoolKind::And);
+        self
+    }
+
+    /// Adds a `column is not null` where clause. No values are bound.
+    pub fn where_not_null(mut self, column: impl Into<String>) -> Self {
+        self.where_clause.push_many(
+            format!("{} is not null", column.into()),
+            vec![],
+            BoolKind::And,
+        );
+        self
+    }
+
+    /// Conditionally add a [where_clause](ComposableQueryBuilder::where_clause). The given
+    /// callback is lazily evaluated, so it's only called if the condition is true.
+    pub fn where_if(mut self, condition: bool, cb: impl Fn() -> (String, SQLValue)) -> Self {
+        if !condition {
+            return self;
+        }
+
+        let (s, v) = cb();
+        self.where_clause.push(s, v, BoolKind::And);
+
+        self
+    }
+
+    /// Conditionally add an [or_where](ComposableQueryBuilder::or_where) clause. The given
+    /// callback is lazily evaluated, so it's only called if the condition is true.
+    pub fn or_where_if<S: Into<String>>(
+        mut self,
+        condition: bool,
+        cb: impl Fn() -> (S, SQLValue),
+    ) -> Self {
+        if !condition {
+            return self;
+        }
+
+        let (s, v) = cb();
+        self.where_clause.push(s.into(), v, BoolKind::Or);
+
+        self
+    }
+
+    /// Binds a named param, which any clause can reference as `:name` instead of a `?`
+    /// placeholder. On Postgres the value is bound once and every reference reuses its
+    /// placeholder, so a value used in several clauses only counts once towards the bind
+    /// parameter limit. MySQL and SQLite have no equivalent, so there the value is bound again
+    /// for each reference.
+    ///
+    /// Params must be bound on the builder that's ultimately built, though sub-builders may
+    /// reference them. Setting a param that's already bound replaces its value.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("messages")
+    ///     .param("user_id", 7)
+    ///     .where_clause("created_at > ?", "2023-01-01".to_string())
+    ///     .where_group(|g| g.or_where_raw("sender_id = :user_id").or_where_raw("recipient_id = :user_id"));
+    ///
+    /// assert_eq!(
+    ///     "select * from messages where created_at > $1 and (sender_id = $2 or recipient_id = $2)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn param(mut self, name: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        let name = name.into();
+        let v = v.into();
+        match self.params.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, existing)) => *existing = v,
+            None => self.params.push((name, v)),
+        }
+        self
+    }
+
+    /// Adds a single having clause. Values are expected to be denoted via a `?` placeholder.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select_many(["user_id", "count(*)"])
+    ///     .where_clause("status_id = ?", 1)
+    ///     .group_by("user_id")
+    ///     .having("count(*) > ?", 5)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!(
+    ///     "select user_id, count(*) from orders where status_id = $1 group by user_id having count(*) > $2",
+    ///     sql
+    /// );
+    /// ```
+    pub fn having(mut self, having: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.having.push(having.into(), v, BoolKind::And);
+        self
+    }
+
+    /// Conditionally add a [having](ComposableQueryBuilder::having) clause. The given
+    /// callback is lazily evaluated, so it's only called if the condition is true.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let min_orders: Option<i64> = Some(5);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .select_many(["user_id", "count(*)"])
+    ///     .group_by("user_id")
+    ///     .having_if(min_orders.is_some(), || ("count(*) >= ?", min_orders.into()));
+    ///
+    /// assert_eq!(
+    ///     "select user_id, count(*) from orders group by user_id having count(*) >= $1",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn having_if<S: Into<String>>(
+        mut self,
+        condition: bool,
+        cb: impl Fn() -> (S, SQLValue),
+    ) -> Self {
+        if !condition {
+            return self;
+        }
+
+        let (s, v) = cb();
+        self.having.push(s.into(), v, BoolKind::And);
+
+        self
+    }
+
+    pub fn having_multi(mut self, having: impl Into<String>, v: Vec<SQLValue>) -> Self {
+        self.having.push_many(having.into(), v, BoolKind::And);
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn limit_opt(mut self, limit: Option<u64>) -> Self {
+        match limit {
+            Some(limit) => self.limit = Some(limit),
+            None => self.limit = None,
+        }
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn offset_opt(mut self, offset: Option<u64>) -> Self {
+        match offset {
+            Some(offset) => self.offset = Some(offset),
+            None => self.offset = None,
+        }
+        self
+    }
+
+    /// Sets the limit from any integer type, erroring on negative values instead of letting
+    /// them wrap.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{BuildError, ComposableQueryBuilder};
+    /// let per_page: i64 = 25;
+    /// let query = ComposableQueryBuilder::new().table("users").try_limit(per_page).unwrap();
+    /// assert_eq!("select * from users limit $1", query.sql());
+    ///
+    /// let err = ComposableQueryBuilder::new().table("users").try_limit(-1).err();
+    /// assert_eq!(
+    ///     Some(BuildError::OutOfRange { clause: "limit".to_string(), value: "-1".to_string() }),
+    ///     err
+    /// );
+    /// ```
+    pub fn try_limit<T>(self, limit: T) -> Result<Self, BuildError>
+    where
+        T: TryInto<u64> + fmt::Display,
+    {
+        let limit = to_u64("limit", limit)?;
+        Ok(self.limit(limit))
+    }
+
+    /// Sets the offset from any integer type, erroring on negative values. See
+    /// [try_limit](ComposableQueryBuilder::try_limit).
+    pub fn try_offset<T>(self, offset: T) -> Result<Self, BuildError>
+    where
+        T: TryInto<u64> + fmt::Display,
+    {
+        let offset = to_u64("offset", offset)?;
+        Ok(self.offset(offset))
+    }
+
+    /// Orders by `column` in the given direction and limits to a single row, ie the most
+    /// recent row with `first("created_at", OrderDir::Desc)`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("orders")
+    ///     .where_clause("user_id = ?", 1)
+    ///     .first("created_at", OrderDir::Desc);
+    ///
+    /// assert_eq!(
+    ///     "select * from orders where user_id = $1 order by created_at desc limit $2",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn first(self, column: impl ToString, dir: OrderDir) -> Self {
+        self.order_by(column, dir).limit(1)
+    }
+
+    /// Like [first](ComposableQueryBuilder::first), but takes the row from the other end, ie
+    /// `last("created_at", OrderDir::Asc)` orders by `created_at desc`.
+    pub fn last(self, column: impl ToString, dir: OrderDir) -> Self {
+        self.first(column, dir.reverse())
+    }
+
+    /// Sets the limit and offset for the given 1-based page. Page `0` is treated as page `1`,
+    /// and the limit and offset are capped at `i64::MAX` so they can always be bound.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .page(3, 25);
+    ///
+    /// assert_eq!("select * from users limit $1 offset $2", query.sql());
+    /// ```
+    pub fn page(self, page: u64, per_page: u64) -> Self {
+        // Capped so they can still be bound as a `bigint`
+        let max = i64::MAX as u64;
+        let offset = page.saturating_sub(1).saturating_mul(per_page).min(max);
+        self.limit(per_page.min(max)).offset(offset)
+    }
+
+    /// Keyset pagination: filters to rows after `value` in the given direction and orders by
+    /// `column`, ie `column > ?` for ascending and `column < ?` for descending.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("status_id = ?", 1)
+    ///     .after_cursor("id", 100, OrderDir::Asc)
+    ///     .limit(25);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where status_id = $1 and id > $2 order by id asc limit $3",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn after_cursor(
+        self,
+        column: impl Into<String>,
+        value: impl Into<SQLValue>,
+        dir: OrderDir,
+    ) -> Self {
+        self.after_cursor_many(vec![(column, value)], dir)
+    }
+
+    /// Keyset pagination over a compound cursor, using a row comparison such as
+    /// `(created_at, id) < (?, ?)`. Every column is ordered in the same direction, which the
+    /// row comparison requires.
+    pub fn after_cursor_many(
+        mut self,
+        cursor: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+        dir: OrderDir,
+    ) -> Self {
+        let (columns, values): (Vec<String>, Vec<SQLValue>) = cursor
+            .into_iter()
+            .map(|(col, v)| (col.into(), v.into()))
+            .unzip();
+        let op = match dir {
+            OrderDir::Asc => ">",
+            OrderDir::Desc => "<",
+        };
+
+        let clause = match columns.len() {
+            0 => return self,
+            1 => format!("{} {} ?", columns[0], op),
+            _ => format!(
+                "({}) {} ({})",
+                columns.join(", "),
+                op,
+                vec!["?"; columns.len()].join(", ")
+            ),
+        };
+
+        self.where_clause.push_many(clause, values, BoolKind::And);
+        self.order_by_many(columns.into_iter().map(|col| (col, dir)))
+    }
+
+    /// Adds a column to the order by clause. Multiple calls are emitted in the order they
+    /// were added.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .order_by("status_id", OrderDir::Asc)
+    ///     .order_by("created_at", OrderDir::Desc)
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users order by status_id asc, created_at desc", sql);
+    /// ```
+    pub fn order_by(mut self, col: impl ToString, dir: OrderDir) -> Self {
+        self.order_by.push((col.to_string(), dir, None));
+        self
+    }
+
+    /// Adds a single column to the order by clause, placing nulls first or last.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, NullsOrder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .order_by_nulls("last_login_at", OrderDir::Desc, NullsOrder::Last);
+    ///
+    /// assert_eq!(
+    ///     "select * from users order by last_login_at desc nulls last",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn order_by_nulls(mut self, col: impl ToString, dir: OrderDir, nulls: NullsOrder) -> Self {
+        self.order_by.push((col.to_string(), dir, Some(nulls)));
+        self
+    }
+
+    /// Adds multiple columns to the order by clause.
+    pub fn order_by_many(
+        mut self,
+        order_by: impl IntoIterator<Item = (impl ToString, OrderDir)>,
+    ) -> Self {
+        self.order_by.extend(
+            order_by
+                .into_iter()
+                .map(|(col, dir)| (col.to_string(), dir, None)),
+        );
+        self
+    }
+
+    /// Sets the columns that may be referenced via
+    /// [order_by_checked](ComposableQueryBuilder::order_by_checked). No columns are allowed
+    /// until this is called.
+    pub fn allowed_columns(mut self, columns: ColumnSet) -> Self {
+        self.allowed_columns = columns;
+        self
+    }
+
+    /// Adds an order by column that may come from user input, erroring if it isn't one of the
+    /// [allowed_columns](ComposableQueryBuilder::allowed_columns).
+    ///
+    /// ```rust
+    /// use composable_query_builder::{BuildError, ColumnSet, ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .allowed_columns(ColumnSet::new(["email", "created_at"]));
+    ///
+    /// let sorted = query.clone().order_by_checked("email", OrderDir::Asc).unwrap();
+    /// assert_eq!("select * from users order by email asc", sorted.sql());
+    ///
+    /// assert!(matches!(
+    ///     query.order_by_checked("password_hash", OrderDir::Asc),
+    ///     Err(BuildError::UnknownColumn { .. })
+    /// ));
+    /// ```
+    pub fn order_by_checked(self, col: impl AsRef<str>, dir: OrderDir) -> Result<Self, BuildError> {
+        let col = self.allowed_columns.check(col.as_ref())?.to_string();
+        Ok(self.order_by(col, dir))
+    }
+
+    /// Adds an order by column that may come from user input, passing it through
+    /// [safe_ident] so anything other than a plain column name is quoted rather than
+    /// interpolated raw. It's quoted for the builder's [dialect](ComposableQueryBuilder::dialect),
+    /// so set that first.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .order_by_safe("email; drop table users", OrderDir::Asc);
+    ///
+    /// assert_eq!(
+    ///     "select * from users order by \"email; drop table users\" asc",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn order_by_safe(self, col: impl AsRef<str>, dir: OrderDir) -> Self {
+        let col = self.dialect.safe_ident(col);
+        self.order_by(col, dir)
+    }
+
+    /// Adds a select column that may come from user input, passing it through [safe_ident]
+    /// like [order_by_safe](ComposableQueryBuilder::order_by_safe).
+    pub fn select_safe(self, col: impl AsRef<str>) -> Self {
+        let col = self.dialect.safe_ident(col);
+        self.select(col)
+    }
+
+    /// Adds an order by column, parsing the direction from a string such as a query param.
+    /// Returns an error if the direction isn't `asc` or `desc`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .order_by_str("email", "DESC")
+    ///     .unwrap();
+    ///
+    /// assert_eq!("select * from users order by email desc", query.sql());
+    /// ```
+    pub fn order_by_str(
+        self,
+        col: impl ToString,
+        dir: impl AsRef<str>,
+    ) -> Result<Self, ParseOrderDirError> {
+        let dir = dir.as_ref().parse::<OrderDir>()?;
+        Ok(self.order_by(col, dir))
+    }
+
+    /// Adds an order by column when one is given, so an optional sort param can be passed
+    /// straight through. `None` leaves the ordering untouched.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, OrderDir};
+    /// let sort = Some(("created_at", OrderDir::Desc));
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .order_by_opt(sort);
+    ///
+    /// assert_eq!("select * from users order by created_at desc", query.sql());
+    /// ```
+    pub fn order_by_opt(self, order_by: Option<(impl ToString, OrderDir)>) -> Self {
+        match order_by {
+            Some((col, dir)) => self.order_by(col, dir),
+            None => self,
+        }
+    }
+
+    /// Adds a single column and value to an insert statement. Columns are emitted in the
+    /// order they were added.
+    pub fn value(mut self, column: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.insert_values.push(column, v);
+        self
+    }
+
+    /// Adds multiple columns and values to an insert statement.
+    pub fn values(
+        mut self,
+        values: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+    ) -> Self {
+        for (column, v) in values {
+            self.insert_values.push(column, v);
+        }
+        self
+    }
+
+    /// Sets the columns of an insert statement, for use with
+    /// [values_many](ComposableQueryBuilder::values_many).
+    pub fn columns(mut self, columns: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.insert_values
+            .set_columns(columns.into_iter().map(|c| c.into()).collect());
+        self
+    }
+
+    /// Adds rows to a multi-row insert, each with a value per column in the order the
+    /// [columns](ComposableQueryBuilder::columns) were given. Rows with the wrong number of
+    /// values are caught by [validate](ComposableQueryBuilder::validate) and
+    /// [try_into_builder](ComposableQueryBuilder::try_into_builder).
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::insert_into("users")
+    ///     .columns(["name", "status_id"])
+    ///     .values_many(vec![
+    ///         vec!["Bob".to_string().into(), 1.into()],
+    ///         vec!["Alice".to_string().into(), 2.into()],
+    ///     ]);
+    ///
+    /// assert_eq!(
+    ///     "insert into users (name, status_id) values ($1, $2), ($3, $4)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn values_many(mut self, rows: impl IntoIterator<Item = Vec<SQLValue>>) -> Self {
+        self.insert_values.push_rows(rows);
+        self
+    }
+
+    /// Sets the columns and rows of a multi-row insert, replacing any values added so far.
+    /// Each row must have a value per column. Pair with
+    /// [into_batched_builders](ComposableQueryBuilder::into_batched_builders) to stay under
+    /// the bind parameter limit when inserting many rows.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::insert_into("users").values_batch(
+    ///     ["name", "status_id"],
+    ///     vec![
+    ///         vec!["Bob".to_string().into(), 1.into()],
+    ///         vec!["Alice".to_string().into(), 2.into()],
+    ///     ],
+    /// );
+    ///
+    /// assert_eq!(
+    ///     "insert into users (name, status_id) values ($1, $2), ($3, $4)",
+    ///     query.sql()
+    /// );
+    /// ```
+    pub fn values_batch(
+        mut self,
+        columns: impl IntoIterator<Item = impl Into<String>>,
+        rows: impl IntoIterator<Item = Vec<SQLValue>>,
+    ) -> Self {
+        self.insert_values.set_rows(
+            columns.into_iter().map(|c| c.into()).collect(),
+            rows.into_iter().collect(),
+        );
+        self
+    }
+
+    /// Adds a single `column = value` assignment to an update statement.
+    pub fn set(mut self, column: impl Into<String>, v: impl Into<SQLValue>) -> Self {
+        self.set.push(column, v);
+        self
+    }
+
+    /// Adds multiple `column = value` assignments to an update statement.
+    pub fn set_many(
+        mut self,
+        set: impl IntoIterator<Item = (impl Into<String>, impl Into<SQLValue>)>,
+    ) -> Self {
+        for (column, v) in set {
+            self.set.push(column, v);
+        }
+        self
+    }
+
+    /// Adds an `on conflict` clause to an insert statement, targeting the given columns. By
+    /// default the conflicting row is left untouched (`do nothing`); use
+    /// [do_update_set](ComposableQueryBuilder::do_update_set) to turn it into an upsert.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::insert_into("users")
+    ///     .value("email", "bob@example.com".to_string())
+    ///     .value("name", "Bob".to_string())
+    ///     .on_conflict(vec!["email"])
+    ///     .do_update_set("name", "excluded.name")
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!(
+    ///     "insert into users (email, name) values ($1, $2) on conflict (email) do update set name = excluded.name",
+    ///     sql
+    /// );
+    /// ```
+    pub fn on_conflict(mut self, target: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.on_conflict = Some(OnConflict::new(
+            target.into_iter().map(|s| s.into()).collect(),
+        ));
+        self
+    }
+
+    /// Sets the `on conflict` action to `do nothing`, discarding any `do update` assignments.
+    pub fn do_nothing(mut self) -> Self {
+        self.on_conflict
+            .get_or_insert_with(|| OnConflict::new(vec![]))
+            .do_nothing();
+        self
+    }
+
+    /// Adds a `column = expr` assignment to the `on conflict do update set` clause. The
+    /// expression is raw sql, such as `excluded.name`.
+    pub fn do_update_set(mut self, column: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.on_conflict
+            .get_or_insert_with(|| OnConflict::new(vec![]))
+            .do_update_set(column, expr);
+        self
+    }
+
+    /// Adds a single column to the returning clause of an insert, update or delete.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::insert_into("users")
+    ///     .value("status_id", 1)
+    ///     .returning("id")
+    ///     .returning("created_at")
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("insert into users (status_id) values ($1) returning id, created_at", sql);
+    /// ```
+    ///
+    /// Columns are emitted in the order they were added. The returning clause is ignored for
+    /// selects.
+    pub fn returning(mut self, returning: impl Into<String>) -> Self {
+        self.returning.push(returning.into());
+        self
+    }
+
+    /// Adds multiple columns to the returning clause of an insert, update or delete.
+    pub fn returning_many(
+        mut self,
+        returning: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.returning
+            .extend(returning.into_iter().map(|s| s.into()));
+        self
+    }
+
+    /// Returns the sql, with `?` placeholders, along with the values to bind.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is a delete statement without any where clauses and
+    /// [allow_delete_all](ComposableQueryBuilder::allow_delete_all) was not called.
+    pub fn parts(mut self) -> (String, Vec<SQLValue>) {
+        let (cte_str, cte_vals) = cte_parts(std::mem::take(&mut self.ctes), self.recursive);
+        let explain = self.explain.take();
+        let raw = std::mem::take(&mut self.raw);
+        let raw_values = std::mem::take(&mut self.raw_values);
+
+        let (statement, statement_values) = match self.query_type {
+            QueryType::Select => self.select_parts(),
+            QueryType::Insert => self.insert_parts(),
+            QueryType::Update => self.update_parts(),
+            QueryType::Delete => self.delete_parts(),
+        };
+
+        // Most queries don't have ctes, so the statement can usually be returned as is
+        let (mut str, mut vals) = if cte_str.is_empty() {
+            (statement, statement_values)
+        } else {
+            let mut str = cte_str;
+            str.push_str(&statement);
+            let mut vals = cte_vals;
+            vals.extend(statement_values);
+            (str, vals)
+        };
+
+        for fragment in raw {
+            str.push(' ');
+            str.push_str(&fragment);
+        }
+        vals.extend(raw_values);
+
+        if let Some(explain) = explain {
+            str.insert(0, ' ');
+            str.insert_str(0, &explain);
+        }
+
+        (str, vals)
+    }
+
+    fn delete_parts(self) -> (String, Vec<SQLValue>) {
+        let only = self.only_prefix();
+        let (table, mut vals) = self.table.parts();
+
+        let mut str = "delete from ".to_string();
+        str.push_str(only);
+        str.push_str(&table);
+
+        let (where_str, where_values) = self.where_clause.parts();
+        str.push_str(&where_str);
+        vals.extend(where_values);
+
+        push_returning(&mut str, &self.returning);
+
+        (str, vals)
+    }
+
+    fn update_parts(self) -> (String, Vec<SQLValue>) {
+        let only = self.only_prefix();
+        let (table, mut vals) = self.table.parts();
+
+        let mut str = "update ".to_string();
+        str.push_str(only);
+        str.push_str(&table);
+
+        // Set values come before the where values
+        let (set_str, set_values) = self.set.parts();
+        str.push_str(&set_str);
+        vals.extend(set_values);
+
+        let (where_str, where_values) = self.where_clause.parts();
+        str.push_str(&where_str);
+        vals.extend(where_values);
+
+        push_returning(&mut str, &self.returning);
+
+        (str, vals)
+    }
+
+    fn insert_parts(self) -> (String, Vec<SQLValue>) {
+        let (table, mut vals) = self.table.parts();
+
+        let mut str = "insert into ".to_string();
+        str.push_str(&table);
+
+        let (values_str, values) = self.insert_values.parts();
+        str.push_str(&values_str);
+        vals.extend(values);
+
+        if let Some(on_conflict) = &self.on_conflict {
+            str.push_str(&on_conflict.sql());
+        }
+
+        push_returning(&mut str, &self.returning);
+
+        (str, vals)
+    }
+
+    fn select_parts(self) -> (String, Vec<SQLValue>) {
+        let only = self.only_prefix();
+        let sample = self.table_sample().cloned();
+        let mut vals = vec![];
+
+        let mut str = String::with_capacity(self.select_len_hint());
+        str.push_str("select ");
+        // let mut str = "select\n    ".to_string();
+
+        match &self.distinct {
+            Some(Distinct::Rows) => str.push_str("distinct "),
+            Some(Distinct::On(on)) => {
+                str.push_str("distinct on (");
+                str.push_str(&on.join(", "));
+                str.push_str(") ");
+            }
+            None => {}
+        }
+
+        if self.select.is_empty() {
+            str.push('*');
+        } else {
+            str.push_str(&self.select.join(", "));
+        }
+        vals.extend(self.select_values);
+        str.push_str(" from ");
+        // str.push_str("\nfrom ");
+
+        str.push_str(only);
+        let (table, table_values) = self.table.parts();
+        str.push_str(&table);
+        vals.extend(table_values);
+
+        if let Some(sample) = sample {
+            let (sample_str, sample_values) = sample.parts();
+            str.push_str(&sample_str);
+            vals.extend(sample_values);
+        }
+
+        // Joins
+        for j in self.joins {
+            str.push(' ');
+            // str.push('\n');
+            str.push_str(&j);
+        }
+        vals.extend(self.join_values);
+
+        // Where clauses
+        let (where_str, str_values) = self.where_clause.parts();
+        str.push_str(&where_str);
+        vals.extend(str_values);
+        if !self.group_by.is_empty() {
+            str.push_str(" group by ");
+            // str.push_str("\ngroup by\n    ");
+            str.push_str(&self.group_by.iter().map(|g| g.sql()).join(", "));
+        }
+
+        // Having clauses
+        let (having_str, having_values) = self.having.parts_with_keyword("having");
+        str.push_str(&having_str);
+        vals.extend(having_values);
+
+        // Unions, which the order by, limit and offset below apply to as a whole
+        for (kind, qb) in self.unions {
+            let wrap = !qb.order_by.is_empty()
+                || qb.limit.is_some()
+                || qb.offset.is_some()
+                || !qb.ctes.is_empty()
+                || !qb.unions.is_empty();
+            let (s, v) = qb.parts();
+
+            str.push(' ');
+            str.push_str(&kind);
+            str.push(' ');
+            if wrap {
+                str.push('(');
+                str.push_str(&s);
+                str.push(')');
+            } else {
+                str.push_str(&s);
+            }
+            vals.extend(v);
+        }
+
+        if !self.order_by.is_empty() {
+            str.push_str(" order by ");
+            str.push_str(
+                &self
+                    .order_by
+                    .iter()
+                    .map(|(col, dir, nulls)| match nulls {
+                        Some(nulls) => format!("{} {} {}", col, dir.as_str(), nulls.as_str()),
+                        None => format!("{} {}", col, dir.as_str()),
+                    })
+                    .join(", "),
+            );
+        }
+
+        if let Some(limit) = self.limit {
+            str.push_str(" limit ?");
+            vals.push(SQLValue::U64(limit));
+        }
+
+        if let Some(offset) = self.offset {
+            str.push_str(" offset ?");
+            vals.push(SQLValue::U64(offset));
+        }
+
+        if let Some(lock) = self.lock {
+            str.push(' ');
+            str.push_str(&lock);
+
+            if let Some(wait) = self.lock_wait {
+                str.push(' ');
+                str.push_str(&wait);
+            }
+        }
+
+        (str, vals)
+    }
+
+    /// The select columns added so far, in order. Empty means `select *`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .select_many(["id", "email"])
+    ///     .join("inner join orgs on orgs.id = users.org_id")
+    ///     .where_clause("users.status_id = ?", 1)
+    ///     .where_group(|g| g.where_clause("orgs.id = ?", 2).or_where("orgs.id = ?", 3));
+    ///
+    /// assert_eq!(vec!["id", "email"], query.selects().collect::<Vec<_>>());
+    /// assert_eq!(
+    ///     vec!["inner join orgs on orgs.id = users.org_id"],
+    ///     query.joins().collect::<Vec<_>>()
+    /// );
+    /// assert_eq!(2, query.where_count());
+    /// ```
+    pub fn selects(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.select.iter().map(|s| s.as_ref())
+    }
+
+    /// The join clauses added so far, in order.
+    pub fn joins(&self) -> impl ExactSizeIterator<Item = &str> + '_ {
+        self.joins.iter().map(|s| s.as_ref())
+    }
+
+    /// The number of top level where clauses. A nested group counts as one.
+    pub fn where_count(&self) -> usize {
+        self.where_clause.len()
+    }
+
+    /// The number of top level having clauses.
+    pub fn having_count(&self) -> usize {
+        self.having.len()
+    }
+
+    /// Returns the number of parameters that will be bound, including the limit and offset,
+    /// without building the sql. Handy for staying under Postgres's limit of 65535 bind
+    /// parameters. Null values are written as a literal `null` and aren't counted.
+    ///
+    /// Named [param](ComposableQueryBuilder::param)s are counted the way the dialect binds
+    /// them: once each for Postgres, once per reference for MySQL and SQLite, and not at all
+    /// when unreferenced. Finding the references renders the sql, so this is only as cheap as
+    /// described when there are no named params.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_in("id", vec![1, 2, 3])
+    ///     .limit(10);
+    ///
+    /// assert_eq!(4, query.param_count());
+    /// ```
+    pub fn param_count(&self) -> usize {
+        let mut count = self.value_count();
+        if !self.params.is_empty() {
+            let (p, _) = self.clone().parts();
+            count +=
+                placeholder::count_param_binds(&p, &self.params, self.dialect.placeholder_style());
+        }
+
+        count
+    }
+
+    /// The number of bound `?` values, leaving out named params. Only the outermost query's
+    /// params are bound, so nested queries are counted the same way.
+    fn value_count(&self) -> usize {
+        let bound = |values: &[SQLValue]| values.iter().filter(|v| v.is_bound()).count();
+
+        let ctes: usize = self.ctes.iter().map(|(_, qb)| qb.value_count()).sum();
+        let raw = bound(&self.raw_values);
+
+        let statement = match self.query_type {
+            QueryType::Select => {
+                let unions: usize = self.unions.iter().map(|(_, qb)| qb.value_count()).sum();
+
+                bound(&self.select_values)
+                    + self.table.param_count()
+                    + self.table_sample().map_or(0, |s| s.param_count())
+                    + bound(&self.join_values)
+                    + self.where_clause.param_count()
+                    + self.having.param_count()
+                    + unions
+                    + usize::from(self.limit.is_some())
+                    + usize::from(self.offset.is_some())
+            }
+            QueryType::Insert => self.table.param_count() + self.insert_values.param_count(),
+            QueryType::Update => {
+                self.table.param_count() + self.set.param_count() + self.where_clause.param_count()
+            }
+            QueryType::Delete => self.table.param_count() + self.where_clause.param_count(),
+        };
+
+        ctes + statement + raw
+    }
+
+    /// Checks the query for a few common mistakes before it's run, returning the first one
+    /// found:
+    ///
+    /// - an empty table name
+    /// - a `?` placeholder count that doesn't match the number of values
+    /// - `distinct on` columns that don't match the leading order by columns
+    /// - a delete without where clauses that wasn't opted in to
+    /// - `only` or `tablesample` on a table or statement that doesn't support them
+    /// - a `u64` value above `i64::MAX`, except with MySQL which binds it natively
+    ///
+    /// Ctes and unioned queries are checked too. This doesn't guarantee the sql is valid.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{BuildError, ComposableQueryBuilder};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .multi_where("id = ? or email = ?", vec![1.into()]);
+    ///
+    /// assert_eq!(
+    ///     Err(BuildError::PlaceholderMismatch { placeholders: 2, values: 1 }),
+    ///     query.validate()
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), BuildError> {
+        let table_is_empty = match &self.table {
+            TableType::Simple(s) => s.trim().is_empty(),
+            TableType::Complex(s, _) => s.trim().is_empty(),
+        };
+        if table_is_empty {
+            return Err(BuildError::EmptyTable);
+        }
+
+        // Each `?` in a complex table template is filled by a sub-builder, not a value
+        if let TableType::Complex(template, parts) = &self.table {
+            let placeholders = placeholder::count(template);
+            if placeholders != parts.len() {
+                return Err(BuildError::PlaceholderMismatch {
+                    placeholders,
+                    values: parts.len(),
+                });
+            }
+            for qb in parts {
+                qb.validate()?;
+            }
+        }
+
+        if let Some(Distinct::On(on)) = &self.distinct {
+            for (col, _, _) in self.order_by.iter().take(on.len()) {
+                if !on.contains(col) {
+                    return Err(BuildError::DistinctOnOrderMismatch {
+                        column: col.clone(),
+                    });
+                }
+            }
+        }
+
+        if self.is_unbounded_delete() {
+            return Err(BuildError::UnboundedDelete);
+        }
+
+        let only_is_ignored =
+            matches!(self.table, TableType::Complex(_, _)) || self.query_type == QueryType::Insert;
+        if self.only && only_is_ignored {
+            return Err(BuildError::UnsupportedTableModifier {
+                modifier: "only".to_string(),
+            });
+        }
+
+        let sample_is_ignored =
+            matches!(self.table, TableType::Complex(_, _)) || self.query_type != QueryType::Select;
+        if self.tablesample.is_some() && sample_is_ignored {
+            return Err(BuildError::UnsupportedTableModifier {
+                modifier: "tablesample".to_string(),
+            });
+        }
+
+        if self.query_type == QueryType::Insert {
+            self.insert_values.check_rows()?;
+        }
+
+        for (_, qb) in &self.ctes {
+            qb.validate()?;
+        }
+        for (_, qb) in &self.unions {
+            qb.validate()?;
+        }
+
+        let (p, v) = self.clone().parts();
+        placeholder::check(&p, &v)?;
+
+        // MySQL binds u64 natively
+        if self.dialect != Dialect::MySql {
+            sql_value::check_u64_range(v.iter().chain(self.params.iter().map(|(_, v)| v)))?;
+        }
+
+        Ok(())
+    }
+
+    /// A rough estimate of the select statement's length, so the output can be allocated
+    /// once rather than growing clause by clause. Sub-queries are estimated generously
+    /// rather than walked.
+    fn select_len_hint(&self) -> usize {
+        let fragments = |v: &[Cow<'static, str>]| v.iter().map(|s| s.len() + 2).sum::<usize>();
+
+        let table = match &self.table {
+            TableType::Simple(s) => s.len(),
+            TableType::Complex(s, parts) => s.len() + parts.len() * 128,
+        };
+        let order_by: usize = self.order_by.iter().map(|(col, _, _)| col.len() + 18).sum();
+
+        // `select`, `from`, `group by`, `limit` and the like
+        64 + table
+            + fragments(&self.select)
+            + fragments(&self.joins)
+            + self
+                .group_by
+                .iter()
+                .map(|g| g.len_hint() + 2)
+                .sum::<usize>()
+            + self.where_clause.len_hint()
+            + self.having.len_hint()
+            + order_by
+            + self.unions.len() * 128
+    }
+
+    /// `only ` when [only](ComposableQueryBuilder::only) applies to the table.
+    fn only_prefix(&self) -> &'static str {
+        match self.table {
+            TableType::Simple(_) if self.only => "only ",
+            _ => "",
+        }
+    }
+
+    /// The [tablesample](ComposableQueryBuilder::tablesample) clause, when it applies to the
+    /// table.
+    fn table_sample(&self) -> Option<&TableSample> {
+        match self.table {
+            TableType::Simple(_) => self.tablesample.as_ref(),
+            TableType::Complex(_, _) => None,
+        }
+    }
+
+    fn schema_table(&self, schema: impl AsRef<str>, table: impl AsRef<str>) -> String {
+        format!(
+            "{}.{}",
+            self.dialect.safe_ident(schema),
+            self.dialect.safe_ident(table)
+        )
+    }
+
+    fn is_unbounded_delete(&self) -> bool {
+        self.query_type == QueryType::Delete
+            && !self.allow_delete_all
+            && self.where_clause.is_empty()
+    }
+
+    /// Errors if this query, or any query nested in it, is an unbounded delete.
+    fn check_unbounded_delete(&self) -> Result<(), BuildError> {
+        if self.is_unbounded_delete() {
+            return Err(BuildError::UnboundedDelete);
+        }
+        if let TableType::Complex(_, parts) = &self.table {
+            parts
+                .iter()
+                .try_for_each(|qb| qb.check_unbounded_delete())?;
+        }
+        self.ctes
+            .iter()
+            .chain(self.unions.iter())
+            .try_for_each(|(_, qb)| qb.check_unbounded_delete())
+    }
+
+    /// Returns the sql with every value inlined as a literal, so it can be pasted into psql.
+    ///
+    /// This is for debugging only. Values are not escaped thoroughly enough to be safe, so
+    /// the output must never be executed or used to build real queries.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("id = ?", 1)
+    ///     .where_clause("name = ?", "O'Brien".to_string());
+    ///
+    /// assert_eq!(
+    ///     "select * from users where id = 1 and name = 'O''Brien'",
+    ///     query.to_debug_sql()
+    /// );
+    /// ```
+    pub fn to_debug_sql(&self) -> String {
+        let (p, v) = self.clone().parts();
+        let mut out = String::new();
+
+        for segment in placeholder::segments_with_params(&p, v, &self.params) {
+            match segment {
+                Segment::Sql(s) => out.push_str(s),
+                Segment::Value(v) | Segment::Param { value: v, .. } => {
+                    out.push_str(&v.to_debug_sql())
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Returns the final sql, with placeholders for the builder's [Dialect], without consuming
+    /// the builder. This matches what `into_builder().sql()` (or the equivalent for the other
+    /// dialects) would return.
+    ///
+    /// ```rust
+    /// use composable_query_builder::ComposableQueryBuilder;
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("id = ?", 1);
+    ///
+    /// assert_eq!("select * from users where id = $1", query.sql());
+    /// ```
+    pub fn sql(&self) -> String {
+        self.sql_with(self.dialect.placeholder_style())
+    }
+
+    /// Returns the final sql using the given placeholder style, regardless of the builder's
+    /// [Dialect]. Useful for feeding the sql to tools outside of sqlx.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, PlaceholderStyle};
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("id = ?", 1)
+    ///     .where_clause("status_id = ?", 2);
+    ///
+    /// assert_eq!(
+    ///     "select * from users where id = :p1 and status_id = :p2",
+    ///     query.sql_with(PlaceholderStyle::Named)
+    /// );
+    /// ```
+    pub fn sql_with(&self, style: PlaceholderStyle) -> String {
+        let (p, v) = self.clone().parts();
+        placeholder::render(&p, v, &self.params, style)
+    }
+
+    /// Builds a Postgres `QueryBuilder` with every value bound.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a delete without where clauses, unless
+    /// [allow_delete_all](ComposableQueryBuilder::allow_delete_all) was called, including one
+    /// nested in a cte, union or subquery. Also panics on a `u64` value that doesn't fit in an
+    /// `i64`. Use [try_into_builder](ComposableQueryBuilder::try_into_builder) to get an error
+    /// instead.
+    pub fn into_builder<'args>(mut self) -> QueryBuilder<'args, Postgres> {
+        if let Err(e) = self.check_unbounded_delete() {
+            panic!("{}", e);
+        }
+
+        let params = std::mem::take(&mut self.params);
+        let (p, v) = self.parts();
+        match postgres_builder(&p, v, &params) {
+            Ok(qb) => qb,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Builds a multi-row insert as one or more statements, splitting the rows so that no
+    /// statement binds more than `max_params` parameters. Use [POSTGRES_MAX_PARAMS] for
+    /// Postgres's limit. Anything other than an insert is built as a single statement.
+    ///
+    /// Each statement is checked like [try_into_builder](ComposableQueryBuilder::try_into_builder),
+    /// and this errors with [BuildError::TooManyParams] when a single row doesn't fit.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, SQLValue};
+    /// let rows = (1..=5).map(|i| vec![SQLValue::from(i), SQLValue::from(i * 10)]);
+    /// let builders = ComposableQueryBuilder::insert_into("scores")
+    ///     .values_batch(["user_id", "score"], rows)
+    ///     .into_batched_builders(4)
+    ///     .unwrap();
+    ///
+    /// let sql: Vec<String> = builders.iter().map(|qb| qb.sql().to_string()).collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         "insert into scores (user_id, score) values ($1, $2), ($3, $4)",
+    ///         "insert into scores (user_id, score) values ($1, $2), ($3, $4)",
+    ///         "insert into scores (user_id, score) values ($1, $2)",
+    ///     ],
+    ///     sql
+    /// );
+    /// ```
+    pub fn into_batched_builders<'args>(
+        mut self,
+        max_params: usize,
+    ) -> Result<Vec<QueryBuilder<'args, Postgres>>, BuildError> {
+        if self.query_type != QueryType::Insert {
+            return Ok(vec![self.try_into_builder()?]);
+        }
+        self.insert_values.check_rows()?;
+
+        // Params outside of the rows, ie from ctes, are repeated in every statement
+        let shared = self.param_count() - self.insert_values.param_count();
+
+        let insert_values = std::mem::replace(&mut self.insert_values, InsertValues::new());
+        let chunks = insert_values.chunks(max_params, shared)?;
+        if chunks.is_empty() {
+            return Ok(vec![self.try_into_builder()?]);
+        }
+
+        chunks
+            .into_iter()
+            .map(|chunk| {
+                let mut qb = self.clone();
+                qb.insert_values = chunk;
+                qb.try_into_builder()
+            })
+            .collect()
+    }
+
+    /// Like [into_builder](ComposableQueryBuilder::into_builder), but errors instead of
+    /// building the query when the number of `?` placeholders doesn't match the number of
+    /// values, ie a clause is missing a `?`, or when an insert row doesn't have a value per
+    /// column. Also errors instead of panicking on an unbounded delete or a `u64` value that
+    /// doesn't fit in an `i64`.
+    ///
+    /// ```rust
+    /// use composable_query_builder::{BuildError, ComposableQueryBuilder};
+    /// let result = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .multi_where("id in (?, ?)", vec![1.into(), 2.into(), 3.into()])
+    ///     .try_into_builder();
+    ///
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(BuildError::PlaceholderMismatch { placeholders: 2, values: 3 })
+    /// ));
+    /// ```
+    pub fn try_into_builder<'args>(mut self) -> Result<QueryBuilder<'args, Postgres>, BuildError> {
+        self.check_unbounded_delete()?;
+        if self.query_type == QueryType::Insert {
+            self.insert_values.check_rows()?;
+        }
+
+        let params = std::mem::take(&mut self.params);
+        let (p, v) = self.parts();
+        placeholder::check(&p, &v)?;
+
+        postgres_builder(&p, v, &params)
+    }
+
+    /// Builds a MySQL `QueryBuilder`, using `?` placeholders. See
+    /// [SQLValue::into_bind_mysql] for the values MySQL supports.
+    ///
+    /// # Panics
+    ///
+    /// Panics when binding an array value, or on an unbounded delete like
+    /// [into_builder](ComposableQueryBuilder::into_builder).
+    #[cfg(feature = "mysql")]
+    pub fn into_mysql_builder<'args>(mut self) -> QueryBuilder<'args, MySql> {
+        if let Err(e) = self.check_unbounded_delete() {
+            panic!("{}", e);
+        }
+
+        let mut qb: QueryBuilder<MySql> = QueryBuilder::new("");
+
+        let params = std::mem::take(&mut self.params);
+        let (p, v) = self.parts();
+        for segment in placeholder::segments_with_params(&p, v, &params) {
+            match segment {
+                Segment::Sql(s) => {
+                    qb.push(s);
+                }
+                // Positional placeholders can't be reused, so params are bound every time
+                Segment::Value(v) | Segment::Param { value: v, .. } => {
+                    if let Err(e) = v.into_bind_mysql(&mut qb) {
+                        panic!("{}", e);
+                    }
+                }
+            }
+        }
+
+        qb
+    }
+
+    /// Builds a SQLite `QueryBuilder`, using `?` placeholders. See
+    /// [SQLValue::into_bind_sqlite] for how values without a SQLite equivalent are bound.
+    ///
+    /// # Panics
+    ///
+    /// Panics on a `u64` value that doesn't fit in an `i64`, see
+    /// [validate](ComposableQueryBuilder::validate), or on an unbounded delete like
+    /// [into_builder](ComposableQueryBuilder::into_builder).
+    #[cfg(feature = "sqlite")]
+    pub fn into_sqlite_builder<'args>(mut self) -> QueryBuilder<'args, Sqlite> {
+        if let Err(e) = self.check_unbounded_delete() {
+            panic!("{}", e);
+        }
+
+        let mut qb: QueryBuilder<Sqlite> = QueryBuilder::new("");
+
+        let params = std::mem::take(&mut self.params);
+        let (p, v) = self.parts();
+        for segment in placeholder::segments_with_params(&p, v, &params) {
+            match segment {
+                Segment::Sql(s) => {
+                    qb.push(s);
+                }
+                // Positional placeholders can't be reused, so params are bound every time
+                Segment::Value(v) | Segment::Param { value: v, .. } => {
+                    if let Err(e) = v.into_bind_sqlite(&mut qb) {
+                        panic!("{}", e);
+                    }
+                }
+            }
+        }
+
+        qb
+    }
+}
+
+fn to_u64<T>(clause: &str, value: T) -> Result<u64, BuildError>
+where
+    T: TryInto<u64> + fmt::Display,
+{
+    let display = value.to_string();
+    value.try_into().map_err(|_| BuildError::OutOfRange {
+        clause: clause.to_string(),
+        value: display,
+    })
+}
+
+fn postgres_builder<'args>(
+    p: &str,
+    v: Vec<SQLValue>,
+    params: &[(String, SQLValue)],
+) -> Result<QueryBuilder<'args, Postgres>, BuildError> {
+    let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("");
+
+    for segment in placeholder::segments_with_params(p, v, params) {
+        match segment {
+            Segment::Sql(s) => {
+                qb.push(s);
+            }
+            Segment::Value(v)
+            | Segment::Param {
+                value: v,
+                reuse: None,
+            } => v.into_bind(&mut qb)?,
+            Segment::Param { reuse: Some(n), .. } => {
+                qb.push(format!("${}", n));
+            }
+        }
+    }
+
+    Ok(qb)
+}
+
+fn cte_parts(
+    ctes: Vec<(String, ComposableQueryBuilder)>,
+    recursive: bool,
+) -> (String, Vec<SQLValue>) {
+    if ctes.is_empty() {
+        return ("".to_string(), vec![]);
+    }
+
+    let mut str = "with ".to_string();
+    let mut vals = vec![];
+
+    if recursive {
+        str.push_str("recursive ");
+    }
+
+    for (i, (name, qb)) in ctes.into_iter().enumerate() {
+        if i > 0 {
+            str.push_str(", ");
+        }
+
+        let (s, v) = qb.parts();
+        str.push_str(&name);
+        str.push_str(" as (");
+        str.push_str(&s);
+        str.push(')');
+        vals.extend(v);
+    }
+    str.push(' ');
+
+    (str, vals)
+}
+
+/// The maximum number of bind parameters Postgres allows in a single statement.
+pub const POSTGRES_MAX_PARAMS: usize = 65535;
+
+/// Builds `column <op> (?, ?, ...)` with `count` placeholders.
+fn in_clause(column: String, op: &str, count: usize) -> String {
+    format!("{} {} ({})", column, op, vec!["?"; count].join(", "))
+}
+
+fn push_returning(str: &mut String, returning: &[String]) {
+    if returning.is_empty() {
+        return;
+    }
+
+    str.push_str(" returning ");
+    str.push_str(&returning.join(", "));
+}
+
+/// The operator joining a where or having clause to the clause before it, see
+/// [where_with](ComposableQueryBuilder::where_with). The first clause's kind is ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
+pub enum BoolKind {
+    And,
+    Or,
+}
+
+impl BoolKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BoolKind::And => "and",
+            BoolKind::Or => "or",
+        }
+    }
+}
+
+#[cfg(test)]
+mod composable_query_builder_tests {
+    use std::borrow::Cow;
+
+    use sqlx::{Postgres, QueryBuilder};
+
+    use crate::{
+        is_plain_ident, quote_ident, safe_ident, BoolKind, BuildError, CaseBuilder, ColumnSet,
+        ComposableQueryBuilder, Dialect, NullsOrder, OrderDir, PlaceholderStyle, SQLValue,
+        SampleMethod,
+    };
+
+    #[test]
+    fn or_where_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .or_where("status_id = ?", 1)
+            .or_where("status_id = ?", 2)
+            .or_where("status_id = ?", 3)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where status_id = $1 or status_id = $2 or status_id = $3",
+            query
+        );
+    }
+
+    #[test]
+    fn limit_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .limit(10)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users limit $1", query);
+    }
+
+    #[test]
+    fn limit_opt_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .limit_opt(Some(10))
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users limit $1", query);
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .limit_opt(None)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users", query);
+    }
+
+    #[test]
+    fn offset_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .offset(10)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users offset $1", query);
+    }
+
+    #[test]
+    fn offset_opt_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .offset_opt(Some(10))
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users offset $1", query);
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .offset_opt(None)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users", query);
+    }
+
+    #[test]
+    fn order_by_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by("email", OrderDir::Desc)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users order by email desc", query);
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by("email", OrderDir::Asc)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users order by email asc", query);
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by("email", OrderDir::Asc)
+            .limit(10);
+
+        assert_eq!("select * from users order by email asc limit $1", q.sql());
+    }
+
+    #[test]
+    fn multi_where_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .multi_where(
+                "(orders > ? and orders < ?) or sales > ?",
+                vec![10.into(), 100.into(), 123.45.into()],
+            )
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where (orders > $1 and orders < $2) or sales > $3",
+            query
+        );
+    }
+
+    #[test]
+    fn insert_works() {
+        let q = ComposableQueryBuilder::insert_into("users")
+            .value("name", "Bob".to_string())
+            .values(vec![("status_id", 1), ("age", 30)])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "insert into users (name, status_id, age) values ($1, $2, $3)",
+            query
+        );
+
+        let q = ComposableQueryBuilder::insert_into("users").into_builder();
+        let query = q.sql();
+
+        assert_eq!("insert into users default values", query);
+    }
+
+    #[test]
+    fn update_works() {
+        let q = ComposableQueryBuilder::update("users")
+            .set("status_id", 2)
+            .where_clause("id = ?", 1)
+            .set_many(vec![
+                ("name", "Bob".to_string()),
+                ("email", "bob@example.com".to_string()),
+            ])
+            .where_clause("status_id = ?", 1)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "update users set status_id = $1, name = $2, email = $3 where id = $4 and status_id = $5",
+            query
+        );
+    }
+
+    #[test]
+    fn delete_works() {
+        let q = ComposableQueryBuilder::delete_from("users")
+            .where_clause("id = ?", 1)
+            .where_clause("status_id = ?", 2)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("delete from users where id = $1 and status_id = $2", query);
+
+        let q = ComposableQueryBuilder::delete_from("users")
+            .allow_delete_all()
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("delete from users", query);
+    }
+
+    #[test]
+    #[should_panic(expected = "delete without where clauses")]
+    fn delete_without_where_panics() {
+        ComposableQueryBuilder::delete_from("users").into_builder();
+    }
+
+    #[test]
+    fn unbounded_delete_renders_but_fails_checks() {
+        let q = ComposableQueryBuilder::delete_from("users");
+        assert_eq!("delete from users", q.sql());
+        assert_eq!("delete from users", q.to_debug_sql());
+        assert_eq!(Err(BuildError::UnboundedDelete), q.validate());
+
+        let nested = ComposableQueryBuilder::new()
+            .with("purged", q.returning("id"))
+            .table("purged");
+        assert!(matches!(
+            nested.try_into_builder(),
+            Err(BuildError::UnboundedDelete)
+        ));
+    }
+
+    #[test]
+    fn returning_works() {
+        let q = ComposableQueryBuilder::update("users")
+            .set("status_id", 2)
+            .where_clause("id = ?", 1)
+            .returning_many(vec!["id", "status_id"])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "update users set status_id = $1 where id = $2 returning id, status_id",
+            query
+        );
+
+        let q = ComposableQueryBuilder::delete_from("users")
+            .where_clause("id = ?", 1)
+            .returning("id")
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("delete from users where id = $1 returning id", query);
+    }
+
+    #[test]
+    fn on_conflict_works() {
+        let q = ComposableQueryBuilder::insert_into("users")
+            .value("email", "bob@example.com".to_string())
+            .value("name", "Bob".to_string())
+            .on_conflict(vec!["email"])
+            .do_nothing()
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "insert into users (email, name) values ($1, $2) on conflict (email) do nothing",
+            query
+        );
+
+        let q = ComposableQueryBuilder::insert_into("users")
+            .value("email", "bob@example.com".to_string())
+            .value("name", "Bob".to_string())
+            .on_conflict(vec!["email"])
+            .do_update_set("name", "excluded.name")
+            .do_update_set("updated_at", "now()")
+            .returning("id")
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "insert into users (email, name) values ($1, $2) on conflict (email) do update set name = excluded.name, updated_at = now() returning id",
+            query
+        );
+    }
+
+    #[test]
+    fn where_in_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("email = ?", "bob@example.com".to_string())
+            .where_in("status_id", vec![1, 2, 3])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where email = $1 and status_id in ($2, $3, $4)",
+            query
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_in("status_id", Vec::<i32>::new())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users where 1 = 0", query);
+    }
+
+    #[test]
+    fn where_not_in_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_not_in("status_id", vec![1, 2])
+            .where_clause("email = ?", "bob@example.com".to_string())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where status_id not in ($1, $2) and email = $3",
+            query
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_not_in("status_id", Vec::<i32>::new())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users where 1 = 1", query);
+    }
+
+    #[test]
+    fn where_between_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("user_id = ?", 1)
+            .where_between("total", 10, 100)
+            .where_clause("status_id = ?", 2)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from orders where user_id = $1 and total between $2 and $3 and status_id = $4",
+            query
+        );
+    }
+
+    #[test]
+    fn where_null_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("status_id = ?", 1)
+            .where_null("deleted_at")
+            .where_not_null("verified_at")
+            .where_clause("email = ?", "bob@example.com".to_string())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where status_id = $1 and deleted_at is null and verified_at is not null and email = $2",
+            query
+        );
+    }
+
+    #[test]
+    fn option_values_work() {
+        let status_id: Option<i32> = None;
+        let q = ComposableQueryBuilder::update("users")
+            .set("status_id", status_id)
+            .set("age", Some(30))
+            .where_clause("id = ?", 1)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "update users set status_id = null, age = $1 where id = $2",
+            query
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_works() {
+        let id = uuid::Uuid::nil();
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", id)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users where id = $1", query);
+    }
+
+    #[test]
+    fn date_values_work() {
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("shipped_on = ?", date)
+            .where_clause("created_at < ?", chrono::Utc::now())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from orders where shipped_on = $1 and created_at < $2",
+            query
+        );
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn decimal_works() {
+        let amount = rust_decimal::Decimal::new(12345, 2);
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("amount > ?", amount)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from orders where amount > $1", query);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("status_id = ?", 1)
+            .where_clause("settings @> ?", serde_json::json!({ "theme": "dark" }))
+            .where_clause("data ->> 'plan' = ?", "pro".to_string())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where status_id = $1 and settings @> $2 and data ->> 'plan' = $3",
+            query
+        );
+    }
+
+    #[test]
+    fn bytes_and_vec_i64_coexist() {
+        assert!(matches!(SQLValue::from(vec![1u8, 2]), SQLValue::Bytes(_)));
+        assert!(matches!(SQLValue::from(vec![1i64, 2]), SQLValue::VecI64(_)));
+
+        let q = ComposableQueryBuilder::new()
+            .table("files")
+            .where_clause("checksum = ?", vec![0xdeu8, 0xad, 0xbe, 0xef])
+            .where_clause("owner_id = any(?)", vec![1i64, 2, 3])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from files where checksum = $1 and owner_id = any($2)",
+            query
+        );
+    }
+
+    #[test]
+    fn array_values_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("posts")
+            .where_clause("tags && ?", vec!["rust".to_string(), "sql".to_string()])
+            .where_clause("category_id = any(?)", vec![1i32, 2])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from posts where tags && $1 and category_id = any($2)",
+            query
+        );
+    }
+
+    #[test]
+    fn order_by_many_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by("status_id", OrderDir::Asc)
+            .order_by_many(vec![("created_at", OrderDir::Desc), ("id", OrderDir::Asc)])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users order by status_id asc, created_at desc, id asc",
+            query
+        );
+    }
+
+    #[test]
+    fn having_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_many(["user_id", "sum(total)"])
+            .where_clause("status_id = ?", 1)
+            .group_by("user_id")
+            .having("count(*) > ?", 5)
+            .where_clause("created_at > ?", 2)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select user_id, sum(total) from orders where status_id = $1 and created_at > $2 group by user_id having count(*) > $3",
+            query
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_many(["user_id", "sum(total)"])
+            .group_by("user_id")
+            .having_multi("sum(total) between ? and ?", vec![10.into(), 100.into()])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select user_id, sum(total) from orders group by user_id having sum(total) between $1 and $2",
+            query
+        );
+    }
+
+    #[test]
+    fn where_clause_and_multi_where_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("status_id = ?", 1)
+            .multi_where(
+                "((orders > ? and orders < ?) or sales > ?)",
+                vec![10.into(), 100.into(), 123.45.into()],
+            )
+            .where_clause("email = ?", "bob@example.com".to_string())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where status_id = $1 and ((orders > $2 and orders < $3) or sales > $4) and email = $5",
+            query
+        );
+    }
+
+    #[test]
+    fn or_multi_where_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .or_multi_where("(orders > ? and orders < ?)", vec![10.into(), 100.into()])
+            .or_multi_where("(sales > ? and sales < ?)", vec![1.into(), 5.into()])
+            .multi_where("(logins > ? and logins < ?)", vec![2.into(), 3.into()])
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where (orders > $1 and orders < $2) or (sales > $3 and sales < $4) and (logins > $5 and logins < $6)",
+            query
+        );
+    }
+
+    #[test]
+    fn where_group_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("org_id = ?", 1)
+            .where_group(|g| {
+                g.or_where("status_id = ?", 2)
+                    .or_where_group(|g| g.where_clause("age > ?", 18).where_clause("age < ?", 65))
+                    .where_in("role_id", vec![3, 4])
+            })
+            .where_group(|g| g)
+            .where_clause("email = ?", "bob@example.com".to_string())
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where org_id = $1 and (status_id = $2 or (age > $3 and age < $4) and role_id in ($5, $6)) and email = $7",
+            query
+        );
+    }
+
+    #[test]
+    fn where_like_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_like("email", "%@example.com")
+            .where_ilike("name", "bob%")
+            .where_contains("bio", "100%_\\")
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select * from users where email like $1 and name ilike $2 and bio like $3 escape '\\'",
+            query
+        );
+
+        let sqlite = ComposableQueryBuilder::new()
+            .dialect(Dialect::Sqlite)
+            .table("users")
+            .where_contains("bio", "100%");
+        assert_eq!(
+            "select * from users where bio like ? escape '\\'",
+            sqlite.sql()
+        );
+
+        let mysql = ComposableQueryBuilder::new()
+            .dialect(Dialect::MySql)
+            .table("users")
+            .where_contains("bio", "100%");
+        assert_eq!(
+            "select * from users where bio like ? escape '\\\\'",
+            mysql.sql()
+        );
+
+        let (_, values) = ComposableQueryBuilder::new()
+            .table("users")
+            .where_contains("bio", "100%_\\")
+            .parts();
+        assert!(matches!(&values[0], SQLValue::String(s) if s == "%100\\%\\_\\\\%"));
+    }
+
+    #[test]
+    fn distinct_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select("email")
+            .distinct()
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select distinct email from users", query);
+
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .distinct_on(["user_id", "status_id"])
+            .where_clause("total > ?", 10)
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select distinct on (user_id, status_id) * from orders where total > $1",
+            query
+        );
+    }
+
+    #[test]
+    fn count_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .join("left join orders on orders.user_id = users.id")
+            .where_clause("users.status_id = ?", 1)
+            .order_by("users.email", OrderDir::Asc)
+            .limit(10);
+
+        let count = q.count_query().into_builder();
+        assert_eq!(
+            "select count(*) from users left join orders on orders.user_id = users.id where users.status_id = $1",
+            count.sql()
+        );
+
+        let count = q.clone().count_distinct("users.id").into_builder();
+        assert_eq!(
+            "select count(distinct users.id) from users left join orders on orders.user_id = users.id where users.status_id = $1",
+            count.sql()
+        );
+
+        // The original query is left untouched
+        let q = q.into_builder();
+        assert!(q.sql().ends_with("limit $2"));
+    }
+
+    #[test]
+    fn count_wraps_row_changing_queries() {
+        let base = || {
+            ComposableQueryBuilder::new()
+                .table("orders")
+                .where_clause("status_id = ?", 1)
+                .order_by("user_id", OrderDir::Asc)
+                .limit(10)
+        };
+
+        let q = base().select("user_id").distinct().count();
+        assert_eq!(
+            "select count(*) from (select distinct user_id from orders where status_id = $1) as t",
+            q.sql()
+        );
+
+        let q = base().distinct_on(["user_id"]).count_distinct("user_id");
+        assert_eq!(
+            "select count(distinct user_id) from (select distinct on (user_id) * from orders where status_id = $1) as t",
+            q.sql()
+        );
+
+        let q = base().select("user_id").group_by("user_id").count();
+        assert_eq!(
+            "select count(*) from (select user_id from orders where status_id = $1 group by user_id) as t",
+            q.sql()
+        );
+
+        let q = base()
+            .select("user_id")
+            .group_by("user_id")
+            .having("sum(total) > ?", 100)
+            .count();
+        assert_eq!(
+            "select count(*) from (select user_id from orders where status_id = $1 group by user_id having sum(total) > $2) as t",
+            q.sql()
+        );
+
+        let q = base()
+            .select("user_id")
+            .union(
+                ComposableQueryBuilder::new()
+                    .table("refunds")
+                    .select("user_id"),
+            )
+            .count();
+        assert_eq!(
+            "select count(*) from (select user_id from orders where status_id = $1 union select user_id from refunds) as t",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn merge_works() {
+        let base = ComposableQueryBuilder::new()
+            .table("users")
+            .select("users.*")
+            .join("left join orders on orders.user_id = users.id")
+            .or_where("users.status_id = ?", 1)
+            .or_where("users.status_id = ?", 2)
+            .limit(10);
+        let filters = ComposableQueryBuilder::new()
+            .select("orders.total")
+            .where_clause("orders.total > ?", 100)
+            .limit(5);
+
+        let q = base.merge(filters).into_builder();
+        let query = q.sql();
+
+        assert_eq!(
+            "select users.*, orders.total from users left join orders on orders.user_id = users.id where (users.status_id = $1 or users.status_id = $2) and (orders.total > $3) limit $4",
+            query
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .merge(ComposableQueryBuilder::new().table("accounts"))
+            .merge(ComposableQueryBuilder::new().where_clause("id = ?", 1))
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from accounts where id = $1", query);
+    }
+
+    #[test]
+    fn clear_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select("email")
+            .join("left join orders on orders.user_id = users.id")
+            .where_clause("status_id = ?", 1)
+            .order_by("email", OrderDir::Asc)
+            .limit(10)
+            .clear_where()
+            .clear_order_by()
+            .clear_limit()
+            .clear_select()
+            .clear_joins()
+            .into_builder();
+        let query = q.sql();
+
+        assert_eq!("select * from users", query);
+    }
+
+    #[test]
+    fn to_debug_sql_works() {
+        let date = chrono::NaiveDate::from_ymd_opt(2023, 7, 1).unwrap();
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("name = ?", "O'Brien".to_string())
+            .where_clause("created_at > ?", date.and_hms_opt(12, 30, 0).unwrap())
+            .where_clause("deleted_at = ?", None::<i32>)
+            .where_clause("tags && ?", vec!["a".to_string(), "b".to_string()])
+            .where_clause("score > ?", 1.5)
+            .where_clause("active = ?", true);
+
+        assert_eq!(
+            "select * from users where name = 'O''Brien' and created_at > '2023-07-01T12:30:00' and deleted_at = null and tags && array['a', 'b'] and score > 1.5 and active = true",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn sql_matches_into_builder() {
+        let q = ComposableQueryBuilder::update("users")
+            .set("status_id", None::<i32>)
+            .set("name", "Bob".to_string())
+            .where_in("id", vec![1, 2])
+            .returning("id");
+
+        assert_eq!(
+            "update users set status_id = null, name = $1 where id in ($2, $3) returning id",
+            q.sql()
+        );
+        assert_eq!(q.sql(), q.clone().into_builder().sql());
+    }
+
+    #[test]
+    fn where_not_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("org_id = ?", 1)
+            .where_not("status_id = ?", 2)
+            .where_clause("email = ?", "bob@example.com".to_string());
+
+        assert_eq!(
+            "select * from users where org_id = $1 and not (status_id = $2) and email = $3",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn typed_joins_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .inner_join("orders", "orders.user_id = users.id")
+            .left_join("refunds", "refunds.order_id = orders.id")
+            .right_join("accounts", "accounts.id = users.account_id")
+            .cross_join("settings");
+
+        assert_eq!(
+            "select * from users inner join orders on orders.user_id = users.id left join refunds on refunds.order_id = orders.id right join accounts on accounts.id = users.account_id cross join settings",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn join_with_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("users.org_id = ?", 1)
+            .join_with(
+                "left join orders on orders.user_id = users.id and orders.total > ?",
+                vec![100.into()],
+            )
+            .left_join("refunds", "refunds.order_id = orders.id")
+            .join_with(
+                "left join notes on notes.user_id = users.id and notes.kind in (?, ?)",
+                vec![3.into(), 4.into()],
+            );
+
+        assert_eq!(
+            "select * from users left join orders on orders.user_id = users.id and orders.total > $1 left join refunds on refunds.order_id = orders.id left join notes on notes.user_id = users.id and notes.kind in ($2, $3) where users.org_id = $4",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn with_works() {
+        let active_users = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("status_id = ?", 1);
+        let big_orders = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("total > ?", 100);
+        let q = ComposableQueryBuilder::new()
+            .table("active_users")
+            .where_clause("org_id = ?", 2)
+            .with("active_users", active_users)
+            .with("big_orders", big_orders)
+            .join("inner join big_orders on big_orders.user_id = active_users.id");
+
+        assert_eq!(
+            "with active_users as (select * from users where status_id = $1), big_orders as (select * from orders where total > $2) select * from active_users inner join big_orders on big_orders.user_id = active_users.id where org_id = $3",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("stale")
+            .with_recursive(
+                "stale(id)",
+                ComposableQueryBuilder::new()
+                    .table("users")
+                    .select("id")
+                    .where_clause("last_seen < ?", 30),
+            )
+            .where_clause("id > ?", 10);
+
+        assert_eq!(
+            "with recursive stale(id) as (select id from users where last_seen < $1) select * from stale where id > $2",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn union_works() {
+        let admins = ComposableQueryBuilder::new()
+            .table("admins")
+            .select("email")
+            .where_clause("org_id = ?", 2);
+        let guests = ComposableQueryBuilder::new()
+            .table("guests")
+            .select("email")
+            .where_clause("org_id = ?", 3)
+            .order_by("created_at", OrderDir::Desc);
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select("email")
+            .where_clause("org_id = ?", 1)
+            .union(admins)
+            .union_all(guests)
+            .limit(10);
+
+        assert_eq!(
+            "select email from users where org_id = $1 union select email from admins where org_id = $2 union all (select email from guests where org_id = $3 order by created_at desc) limit $4",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn nested_union_works() {
+        let table = |name: &'static str| ComposableQueryBuilder::new().table(name).select("id");
+        let q = table("a").union(table("b").union_all(table("c")));
+
+        assert_eq!(
+            "select id from a union (select id from b union all select id from c)",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn where_in_subquery_works() {
+        let subquery = ComposableQueryBuilder::new()
+            .table("orders")
+            .select("user_id")
+            .where_clause("total > ?", 100)
+            .where_in("status_id", vec![2, 3]);
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("org_id = ?", 1)
+            .where_in_subquery("id", subquery)
+            .where_clause("email = ?", "bob@example.com".to_string());
+
+        assert_eq!(
+            "select * from users where org_id = $1 and id in (select user_id from orders where total > $2 and status_id in ($3, $4)) and email = $5",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn locking_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("jobs")
+            .where_clause("status_id = ?", 1)
+            .for_update()
+            .skip_locked();
+
+        assert_eq!(
+            "select * from jobs where status_id = $1 for update skip locked",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("jobs")
+            .for_share()
+            .no_wait();
+
+        assert_eq!("select * from jobs for share nowait", q.sql());
+    }
+
+    #[test]
+    fn where_limit_and_offset_work_together() {
+        let q = ComposableQueryBuilder::new()
+            .table("jobs")
+            .where_clause("status_id = ?", 1)
+            .limit(10)
+            .offset(20)
+            .for_update()
+            .skip_locked();
+
+        assert_eq!(
+            "select * from jobs where status_id = $1 limit $2 offset $3 for update skip locked",
+            q.clone().into_builder().sql()
+        );
+        assert_eq!(q.sql(), q.clone().into_builder().sql());
+    }
+
+    #[test]
+    fn mysql_dialect_works() {
+        let q = ComposableQueryBuilder::new()
+            .dialect(Dialect::MySql)
+            .table("users")
+            .where_clause("status_id = ?", 1)
+            .where_clause("deleted_at = ?", None::<i32>)
+            .where_in("role_id", vec![2, 3])
+            .limit(10);
+
+        assert_eq!(
+            "select * from users where status_id = ? and deleted_at = null and role_id in (?, ?) limit ?",
+            q.sql()
+        );
+
+        #[cfg(feature = "mysql")]
+        {
+            assert_eq!(q.sql(), q.clone().into_mysql_builder().sql());
+
+            let mut qb = sqlx::QueryBuilder::<sqlx::MySql>::new("select * from users where id in ");
+            assert_eq!(
+                Err(BuildError::UnsupportedArray {
+                    dialect: Dialect::MySql
+                }),
+                SQLValue::VecI32(vec![1, 2]).into_bind_mysql(&mut qb)
+            );
+            assert_eq!("select * from users where id in ", qb.sql());
+        }
+    }
+
+    #[test]
+    fn sqlite_dialect_works() {
+        let q = ComposableQueryBuilder::new()
+            .dialect(Dialect::Sqlite)
+            .table("users")
+            .where_clause("status_id = ?", 1)
+            .where_clause("id in (select value from json_each(?))", vec![1i64, 2])
+            .limit(10);
+
+        assert_eq!(
+            "select * from users where status_id = ? and id in (select value from json_each(?)) limit ?",
+            q.sql()
+        );
+
+        #[cfg(feature = "sqlite")]
+        assert_eq!(q.sql(), q.clone().into_sqlite_builder().sql());
+    }
+
+    #[test]
+    fn sql_with_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", 1)
+            .where_clause("deleted_at = ?", None::<i32>)
+            .limit(10);
+
+        assert_eq!(
+            "select * from users where id = $1 and deleted_at = null limit $2",
+            q.sql_with(PlaceholderStyle::Numbered)
+        );
+        assert_eq!(
+            "select * from users where id = ? and deleted_at = null limit ?",
+            q.sql_with(PlaceholderStyle::Positional)
+        );
+        assert_eq!(
+            "select * from users where id = :p1 and deleted_at = null limit :p2",
+            q.sql_with(PlaceholderStyle::Named)
+        );
+    }
+
+    #[test]
+    fn page_works() {
+        let q = ComposableQueryBuilder::new().table("users").page(3, 25);
+        assert_eq!("select * from users limit 25 offset 50", q.to_debug_sql());
+
+        let q = ComposableQueryBuilder::new().table("users").page(0, 25);
+        assert_eq!("select * from users limit 25 offset 0", q.to_debug_sql());
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .page(u64::MAX, 25);
+        assert_eq!(
+            format!("select * from users limit 25 offset {}", i64::MAX),
+            q.to_debug_sql()
+        );
+        assert!(q.try_into_builder().is_ok());
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .page(2, u64::MAX);
+        assert_eq!(
+            format!("select * from users limit {0} offset {0}", i64::MAX),
+            q.to_debug_sql()
+        );
+        q.into_builder();
+    }
+
+    #[test]
+    fn after_cursor_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .after_cursor("id", 100, OrderDir::Desc)
+            .where_clause("status_id = ?", 1)
+            .limit(25);
+
+        assert_eq!(
+            "select * from users where id < $1 and status_id = $2 order by id desc limit $3",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("user_id = ?", 1)
+            .after_cursor_many(vec![("created_at", 10), ("id", 20)], OrderDir::Asc);
+
+        assert_eq!(
+            "select * from orders where user_id = $1 and (created_at, id) > ($2, $3) order by created_at asc, id asc",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn raw_fragments_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .raw_select(
+                "row_number() over (partition by user_id order by created_at) as rn",
+                vec![],
+            )
+            .raw_select("total > ? as is_large", vec![100.into()])
+            .join_with(
+                "left join users on users.id = orders.user_id and users.org_id = ?",
+                vec![2.into()],
+            )
+            .where_clause("status_id = ?", 3)
+            .append_raw("fetch first ? rows only", vec![5.into()]);
+
+        assert_eq!(
+            "select row_number() over (partition by user_id order by created_at) as rn, total > 100 as is_large from orders left join users on users.id = orders.user_id and users.org_id = 2 where status_id = 3 fetch first 5 rows only",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn select_as_works() {
+        let alias = "order_total".to_string();
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_as("sum(total)", &alias)
+            .select_as("count(*)", "n");
+
+        assert_eq!(
+            "select sum(total) as order_total, count(*) as n from orders",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn select_if_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select("id")
+            .select_if(true, || "email")
+            .select_if(false, || -> String { panic!("should not be evaluated") });
+
+        assert_eq!("select id, email from users", q.sql());
+    }
+
+    #[test]
+    fn join_if_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .join_if(false, || -> String { panic!("should not be evaluated") })
+            .join_if(true, || "left join orders on orders.user_id = users.id");
+
+        assert_eq!(
+            "select * from users left join orders on orders.user_id = users.id",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn group_by_if_works() {
+        let by_status = true;
+        let by_org = false;
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select_if(by_status, || "status_id")
+            .select_if(by_org, || "org_id")
+            .select("count(*)")
+            .group_by_if(by_status, || "status_id")
+            .group_by_if(by_org, || "org_id");
+
+        assert_eq!(
+            "select status_id, count(*) from users group by status_id",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn order_by_opt_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by_opt(None::<(String, OrderDir)>);
+        assert_eq!("select * from users", q.sql());
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by("status_id", OrderDir::Asc)
+            .order_by_opt(Some(("id".to_string(), OrderDir::Desc)));
+        assert_eq!(
+            "select * from users order by status_id asc, id desc",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn order_by_str_works() {
+        assert_eq!(Ok(OrderDir::Asc), "asc".parse::<OrderDir>());
+        assert_eq!(Ok(OrderDir::Desc), " Desc ".parse::<OrderDir>());
+        assert_eq!("desc", OrderDir::Desc.to_string());
+        assert_eq!("Asc", format!("{:?}", OrderDir::Asc));
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by_str("email", "desc")
+            .unwrap();
+        assert_eq!("select * from users order by email desc", q.sql());
+
+        let err = match ComposableQueryBuilder::new().order_by_str("email", "sideways") {
+            Ok(_) => panic!("expected an invalid direction error"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            "invalid order direction `sideways`, expected `asc` or `desc`",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn order_by_nulls_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .order_by_nulls("last_login_at", OrderDir::Desc, NullsOrder::Last)
+            .order_by_nulls("deleted_at", OrderDir::Asc, NullsOrder::First)
+            .order_by("id", OrderDir::Asc);
+
+        assert_eq!(
+            "select * from users order by last_login_at desc nulls last, deleted_at asc nulls first, id asc",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn window_select_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .window_select(
+                "running_total",
+                "sum(total)",
+                ["user_id", "org_id"],
+                [("created_at", OrderDir::Asc), ("id", OrderDir::Asc)],
+            )
+            .window_select(
+                "overall",
+                "count(*)",
+                Vec::<String>::new(),
+                Vec::<(String, OrderDir)>::new(),
+            );
+
+        assert_eq!(
+            "select sum(total) over (partition by user_id, org_id order by created_at asc, id asc) as running_total, count(*) over () as overall from orders",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn select_table_star_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select_table_star("users")
+            .select_as("orders.id", "order_id")
+            .left_join("orders", "orders.user_id = users.id");
+
+        assert_eq!(
+            "select users.*, orders.id as order_id from users left join orders on orders.user_id = users.id",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn validate_works() {
+        let ok = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", 1)
+            .where_null("deleted_at");
+        assert_eq!(Ok(()), ok.validate());
+
+        assert_eq!(
+            Err(BuildError::EmptyTable),
+            ComposableQueryBuilder::new().validate()
+        );
+
+        let extra_value = ComposableQueryBuilder::new()
+            .table("users")
+            .multi_where("id = 1", vec![1.into()]);
+        assert_eq!(
+            Err(BuildError::PlaceholderMismatch {
+                placeholders: 0,
+                values: 1
+            }),
+            extra_value.validate()
+        );
+
+        let distinct_on = ComposableQueryBuilder::new()
+            .table("orders")
+            .distinct_on(["user_id"])
+            .order_by("created_at", OrderDir::Desc);
+        assert_eq!(
+            Err(BuildError::DistinctOnOrderMismatch {
+                column: "created_at".to_string()
+            }),
+            distinct_on.validate()
+        );
+        assert_eq!(
+            Ok(()),
+            ComposableQueryBuilder::new()
+                .table("orders")
+                .distinct_on(["user_id"])
+                .order_by("user_id", OrderDir::Asc)
+                .order_by("created_at", OrderDir::Desc)
+                .validate()
+        );
+
+        let inner_cte = ComposableQueryBuilder::new()
+            .table("users")
+            .with("recent", ComposableQueryBuilder::new().table(""));
+        assert_eq!(Err(BuildError::EmptyTable), inner_cte.validate());
+
+        assert_eq!(
+            Err(BuildError::UnboundedDelete),
+            ComposableQueryBuilder::delete_from("users").validate()
+        );
+    }
+
+    #[test]
+    fn try_into_builder_works() {
+        let qb = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", 1)
+            .try_into_builder()
+            .unwrap();
+        assert_eq!("select * from users where id = $1", qb.sql());
+
+        let missing_placeholder = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = 1", 1)
+            .try_into_builder();
+        assert!(matches!(
+            missing_placeholder,
+            Err(BuildError::PlaceholderMismatch {
+                placeholders: 0,
+                values: 1
+            })
+        ));
+
+        assert!(matches!(
+            ComposableQueryBuilder::delete_from("users").try_into_builder(),
+            Err(BuildError::UnboundedDelete)
+        ));
+    }
+
+    #[test]
+    fn escaped_placeholder_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("events")
+            .where_clause("data ?? ?", "key".to_string())
+            .where_clause("user_id = ?", 1);
+
+        assert_eq!(
+            "select * from events where data ? $1 and user_id = $2",
+            q.sql()
+        );
+        assert_eq!(
+            "select * from events where data ? 'key' and user_id = 1",
+            q.to_debug_sql()
+        );
+        assert_eq!(Ok(()), q.validate());
+
+        let q = ComposableQueryBuilder::new()
+            .complex_table(
+                "(select * from events where data ?? 'key') e join (?) u on u.id = e.user_id",
+                vec![ComposableQueryBuilder::new()
+                    .table("users")
+                    .where_clause("org_id = ?", 2)],
+            )
+            .where_clause("e.id = ?", 3);
+
+        assert_eq!(
+            "select * from (select * from events where data ? 'key') e join (select * from users where org_id = $1) u on u.id = e.user_id where e.id = $2",
+            q.into_builder().sql()
+        );
+    }
+
+    #[test]
+    fn ident_quoting_works() {
+        assert_eq!("\"User\"\"s\"", quote_ident("User\"s"));
+        assert!(is_plain_ident("_created_at2"));
+        assert!(!is_plain_ident("2fast"));
+        assert!(!is_plain_ident(""));
+        assert_eq!("\"\"", safe_ident(""));
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select_safe("id")
+            .select_safe("Full Name")
+            .order_by_safe("created_at", OrderDir::Desc);
+
+        assert_eq!(
+            "select id, \"Full Name\" from users order by created_at desc",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn safe_ident_with_placeholders_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("t")
+            .select_safe("a?b")
+            .select_safe("x:id")
+            .where_clause("id = ?", 1)
+            .where_raw("org_id = :id")
+            .param("id", 2);
+
+        assert_eq!(
+            "select \"a?b\", \"x:id\" from t where id = $1 and org_id = $2",
+            q.sql()
+        );
+        assert_eq!(
+            "select \"a?b\", \"x:id\" from t where id = 1 and org_id = 2",
+            q.to_debug_sql()
+        );
+    }
+
+    #[cfg(feature = "mysql")]
+    #[test]
+    fn mysql_ident_quoting_works() {
+        let q = ComposableQueryBuilder::new()
+            .dialect(Dialect::MySql)
+            .table("users")
+            .select_safe("id")
+            .select_safe("Full `Name`")
+            .order_by_safe("created at", OrderDir::Desc);
+
+        assert_eq!(
+            "select id, `Full ``Name``` from users order by `created at` desc",
+            q.into_mysql_builder().sql()
+        );
+        assert_eq!("\"Full Name\"", Dialect::Sqlite.safe_ident("Full Name"));
+    }
+
+    #[test]
+    fn order_by_checked_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .allowed_columns(ColumnSet::new(["email", "created_at"]))
+            .order_by_checked("created_at", OrderDir::Desc)
+            .unwrap();
+        assert_eq!("select * from users order by created_at desc", q.sql());
+
+        let err = match q.order_by_checked("id; drop table users", OrderDir::Asc) {
+            Ok(_) => panic!("expected an unknown column error"),
+            Err(err) => err,
+        };
+        assert_eq!(
+            BuildError::UnknownColumn {
+                column: "id; drop table users".to_string()
+            },
+            err
+        );
+
+        // Nothing is allowed until a column set is given
+        assert!(ComposableQueryBuilder::new()
+            .table("users")
+            .order_by_checked("email", OrderDir::Asc)
+            .is_err());
+    }
+
+    #[test]
+    fn display_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", 1)
+            .limit(10);
+
+        assert_eq!("select * from users where id = ? limit ?", q.to_string());
+        assert_eq!(
+            "query: select * from users where id = ? limit ?",
+            format!("query: {}", q)
+        );
+    }
+
+    #[test]
+    fn display_unbounded_delete_does_not_panic() {
+        let q = ComposableQueryBuilder::delete_from("sessions");
+        assert_eq!("delete from sessions", q.to_string());
+
+        let q = ComposableQueryBuilder::new()
+            .with(
+                "purged",
+                ComposableQueryBuilder::delete_from("sessions").returning("id"),
+            )
+            .table("purged");
+        assert_eq!(
+            "with purged as (delete from sessions returning id) select * from purged",
+            q.to_string()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_works() {
+        let created_at = chrono::NaiveDate::from_ymd_opt(2023, 7, 1)
+            .unwrap()
+            .and_hms_opt(12, 30, 0)
+            .unwrap();
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select("id")
+            .where_clause("created_at > ?", created_at)
+            .or_where_group(|q| q.where_in("status_id", vec![1, 2]))
+            .order_by("id", OrderDir::Desc)
+            .limit(10)
+            .for_update();
+
+        let json = serde_json::to_string(&q).unwrap();
+        assert!(json.contains("\"2023-07-01T12:30:00\""));
+
+        let restored: ComposableQueryBuilder = serde_json::from_str(&json).unwrap();
+        assert_eq!(q.to_debug_sql(), restored.to_debug_sql());
+    }
+
+    #[test]
+    fn static_fragments_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select_static("users.email")
+            .join_static("left join orgs on orgs.id = users.org_id")
+            .where_static("users.status_id = ?", 1)
+            .or_where_static("users.role = ?", "admin")
+            .group_by_static("users.email");
+
+        assert_eq!(
+            "select users.email from users left join orgs on orgs.id = users.org_id where users.status_id = $1 or users.role = $2 group by users.email",
+            q.sql()
+        );
+        assert!(matches!(q.select[0], Cow::Borrowed(_)));
+        assert!(matches!(q.joins[0], Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn borrowed_fragments_work() {
+        let col = String::from("users.email");
+        let join = String::from("left join orgs on orgs.id = users.org_id");
+        let filter = String::from("users.status_id = ?");
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .select(&col)
+            .select(col.as_str())
+            .join(&join)
+            .where_clause(filter.as_str(), 1)
+            .group_by(&col);
+
+        assert_eq!(
+            "select users.email, users.email from users left join orgs on orgs.id = users.org_id where users.status_id = $1 group by users.email",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn merge_wraps_single_or_clause() {
+        let q = ComposableQueryBuilder::new()
+            .table("docs")
+            .multi_where("owner_id = ? or shared = ?", vec![5.into(), true.into()])
+            .merge(ComposableQueryBuilder::new().where_clause("org_id = ?", 1))
+            .having_multi("count(*) > ? or max(size) > ?", vec![1.into(), 2.into()])
+            .merge(ComposableQueryBuilder::new().having("sum(size) < ?", 100));
+
+        assert_eq!(
+            "select * from docs where (owner_id = $1 or shared = $2) and (org_id = $3) having (count(*) > $4 or max(size) > $5) and (sum(size) < $6)",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn partial_eq_works() {
+        let base = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("org_id = ?", 1);
+        let filters = ComposableQueryBuilder::new().where_clause("status_id = ?", 2);
+
+        let expected = ComposableQueryBuilder::new()
+            .table("users")
+            .where_group(|g| g.where_clause("org_id = ?", 1))
+            .where_group(|g| g.where_clause("status_id = ?", 2));
+        assert!(base.clone().merge(filters) == expected);
+        assert!(base != expected);
+
+        assert_eq!(SQLValue::I32(1), SQLValue::from(1));
+        assert_ne!(SQLValue::I32(1), SQLValue::I64(1));
+        assert_ne!(SQLValue::F64(f64::NAN), SQLValue::F64(f64::NAN));
+    }
+
+    #[test]
+    fn param_count_works() {
+        let q = ComposableQueryBuilder::new()
+            .with(
+                "active",
+                ComposableQueryBuilder::new()
+                    .table("users")
+                    .where_clause("status_id = ?", 1),
+            )
+            .table("active")
+            .join_with("left join orders on orders.total > ?", vec![100.into()])
+            .where_clause("name = ?", "bob".to_string())
+            .where_clause("deleted_at is ?", SQLValue::Null)
+            .or_where_group(|q| q.where_in("org_id", vec![1, 2]))
+            .having("count(*) > ?", 2)
+            .union(ComposableQueryBuilder::new().table("admins").limit(5))
+            .limit(10)
+            .offset(20);
+
+        let (p, v) = q.clone().parts();
+        let bound = v.iter().filter(|v| !matches!(v, SQLValue::Null)).count();
+        assert_eq!(9, bound);
+        assert_eq!(bound, q.param_count());
+        assert!(p.contains("deleted_at is ?"));
+
+        let update = ComposableQueryBuilder::update("users")
+            .set("status_id", 2)
+            .where_clause("id = ?", 1)
+            .limit(10);
+        assert_eq!(2, update.param_count());
+    }
+
+    #[test]
+    fn batched_insert_works() {
+        let rows = (1..=5).map(|i| vec![SQLValue::from(i), format!("user {}", i).into()]);
+        let builders = ComposableQueryBuilder::insert_into("users")
+            .values_batch(["id", "name"], rows)
+            .on_conflict(["id"])
+            .do_nothing()
+            .into_batched_builders(6)
+            .unwrap();
+
+        let sql: Vec<String> = builders.iter().map(|qb| qb.sql().to_string()).collect();
+        assert_eq!(
+            vec![
+                "insert into users (id, name) values ($1, $2), ($3, $4), ($5, $6) on conflict (id) do nothing",
+                "insert into users (id, name) values ($1, $2), ($3, $4) on conflict (id) do nothing",
+            ],
+            sql
+        );
+
+        let mismatched = ComposableQueryBuilder::insert_into("users")
+            .values_batch(["id", "name"], vec![vec![1.into()]]);
+        assert_eq!(
+            Err(BuildError::RowLengthMismatch {
+                expected: 2,
+                found: 1
+            }),
+            mismatched.clone().validate()
+        );
+        assert!(matches!(
+            mismatched.into_batched_builders(6),
+            Err(BuildError::RowLengthMismatch { .. })
+        ));
+
+        let rows = (1..=2).map(|i| vec![SQLValue::from(i), format!("user {}", i).into()]);
+        let too_wide = ComposableQueryBuilder::insert_into("users")
+            .values_batch(["id", "name"], rows)
+            .into_batched_builders(1);
+        assert!(matches!(
+            too_wide,
+            Err(BuildError::TooManyParams { max: 1, needed: 2 })
+        ));
+    }
+
+    #[test]
+    fn values_many_works() {
+        let q = ComposableQueryBuilder::insert_into("users")
+            .columns(["name", "status_id"])
+            .values_many(vec![
+                vec!["Bob".to_string().into(), 1.into()],
+                vec!["Alice".to_string().into(), 2.into()],
+            ])
+            .values_many(vec![vec!["Eve".to_string().into(), 3.into()]])
+            .returning("id");
+
+        assert_eq!(
+            "insert into users (name, status_id) values ($1, $2), ($3, $4), ($5, $6) returning id",
+            q.sql()
+        );
+        assert_eq!(
+            "insert into users (name, status_id) values ('Bob', 1), ('Alice', 2), ('Eve', 3) returning id",
+            q.to_debug_sql()
+        );
+
+        let mismatched = ComposableQueryBuilder::insert_into("users")
+            .columns(["name", "status_id"])
+            .values_many(vec![
+                vec!["Bob".to_string().into(), 1.into()],
+                vec!["Alice".to_string().into()],
+            ])
+            .try_into_builder();
+        assert!(matches!(
+            mismatched,
+            Err(BuildError::RowLengthMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        let no_rows = ComposableQueryBuilder::insert_into("users").columns(["name", "status_id"]);
+        assert_eq!(Err(BuildError::InsertWithoutRows), no_rows.validate());
+
+        let no_columns = ComposableQueryBuilder::insert_into("users")
+            .values_many(vec![vec!["Bob".to_string().into(), 1.into()]]);
+        assert_eq!(Err(BuildError::InsertWithoutColumns), no_columns.validate());
+        assert!(matches!(
+            no_columns.try_into_builder(),
+            Err(BuildError::InsertWithoutColumns)
+        ));
+
+        let defaults = ComposableQueryBuilder::insert_into("users");
+        assert_eq!(Ok(()), defaults.validate());
+    }
+
+    #[test]
+    fn table_as_works() {
+        let q = ComposableQueryBuilder::update("users")
+            .table_as("users", "u")
+            .set("status_id", 2)
+            .where_clause("u.id = ?", 1);
+        assert_eq!(
+            "update users as u set status_id = $1 where u.id = $2",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table_as("orders", "o")
+            .inner_join("users u", "u.id = o.user_id")
+            .select_table_star("o");
+        assert_eq!(
+            "select o.* from orders as o inner join users u on u.id = o.user_id",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn table_in_schema_works() {
+        let q = ComposableQueryBuilder::new()
+            .table_in_schema_as("Analytics", "page views", "pv")
+            .select("pv.id");
+        assert_eq!("select pv.id from Analytics.\"page views\" as pv", q.sql());
+
+        let q = ComposableQueryBuilder::delete_from("events")
+            .table_in_schema("archive", "events")
+            .where_clause("id = ?", 1);
+        assert_eq!("delete from archive.events where id = $1", q.sql());
+
+        let q = ComposableQueryBuilder::new()
+            .dialect(Dialect::MySql)
+            .table_in_schema_as("analytics", "page views", "pv")
+            .where_clause("pv.id = ?", 1);
+        assert_eq!(
+            "select * from analytics.`page views` as pv where pv.id = ?",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn complex_table_with_nested_values_works() {
+        let orders = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_many(["user_id", "sum(total) as total"])
+            .where_clause("status_id = ?", 1)
+            .where_in("org_id", vec![2, 3])
+            .group_by("user_id");
+        let refunds = ComposableQueryBuilder::new()
+            .table("refunds")
+            .where_clause("reason = ?", "fraud".to_string())
+            .limit(5);
+
+        let q = ComposableQueryBuilder::new()
+            .complex_table(
+                "(?) as o left join (?) as r on r.user_id = o.user_id",
+                vec![orders, refunds],
+            )
+            .join_with(
+                "inner join users u on u.id = o.user_id and u.org_id = ?",
+                vec![4.into()],
+            )
+            .where_clause("o.total > ?", 500);
+
+        assert_eq!(Ok(()), q.validate());
+        assert_eq!(
+            "select * from (select user_id, sum(total) as total from orders where status_id = 1 and org_id in (2, 3) group by user_id) as o left join (select * from refunds where reason = 'fraud' limit 5) as r on r.user_id = o.user_id inner join users u on u.id = o.user_id and u.org_id = 4 where o.total > 500",
+            q.to_debug_sql()
+        );
+        assert_eq!(
+            "select * from (select user_id, sum(total) as total from orders where status_id = $1 and org_id in ($2, $3) group by user_id) as o left join (select * from refunds where reason = $4 limit $5) as r on r.user_id = o.user_id inner join users u on u.id = o.user_id and u.org_id = $6 where o.total > $7",
+            q.into_builder().sql()
+        );
+
+        let missing_builder = ComposableQueryBuilder::new().complex_table(
+            "(?) as a join (?) as b on a.id = b.id",
+            vec![ComposableQueryBuilder::new().table("a")],
+        );
+        assert_eq!(
+            Err(BuildError::PlaceholderMismatch {
+                placeholders: 2,
+                values: 1
+            }),
+            missing_builder.validate()
+        );
+    }
+
+    #[test]
+    fn from_subquery_works() {
+        let latest = ComposableQueryBuilder::new()
+            .table("orders")
+            .distinct_on(["user_id"])
+            .where_clause("org_id = ?", 1)
+            .order_by("user_id", OrderDir::Asc)
+            .order_by("created_at", OrderDir::Desc);
+
+        let q = ComposableQueryBuilder::new()
+            .from_subquery(latest, "latest")
+            .select("latest.*")
+            .where_clause("latest.total > ?", 50)
+            .limit(10);
+
+        assert_eq!(Ok(()), q.validate());
+        assert_eq!(
+            "select latest.* from (select distinct on (user_id) * from orders where org_id = $1 order by user_id asc, created_at desc) as latest where latest.total > $2 limit $3",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn lateral_join_works() {
+        let recent = ComposableQueryBuilder::new()
+            .table("events")
+            .where_clause(
+                "events.user_id = u.id and events.kind = ?",
+                "login".to_string(),
+            )
+            .limit(3);
+
+        let q = ComposableQueryBuilder::new()
+            .table_as("users", "u")
+            .join_with(
+                "inner join orgs o on o.id = u.org_id and o.plan = ?",
+                vec!["pro".to_string().into()],
+            )
+            .lateral_join(recent, "e")
+            .where_clause("u.id = ?", 7);
+
+        assert_eq!(
+            "select * from users as u inner join orgs o on o.id = u.org_id and o.plan = 'pro' cross join lateral (select * from events where events.user_id = u.id and events.kind = 'login' limit 3) as e where u.id = 7",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn having_if_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_many(["user_id", "sum(total)"])
+            .group_by("user_id")
+            .having_if(true, || ("sum(total) > ?", 100.into()))
+            .having_if(false, || -> (String, SQLValue) {
+                panic!("should not be evaluated")
+            });
+
+        assert_eq!(
+            "select user_id, sum(total) from orders group by user_id having sum(total) > $1",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn or_where_if_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .or_where_if(true, || ("email = ?", "bob@example.com".to_string().into()));
+        assert_eq!("select * from users where email = $1", q.sql());
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("id = ?", 1)
+            .or_where_if(false, || -> (String, SQLValue) {
+                panic!("should not be evaluated")
+            });
+        assert_eq!("select * from users where id = $1", q.sql());
+    }
+
+    #[test]
+    fn where_clause_opt_works() {
+        let email: Option<String> = None;
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause_opt("email = ?", email)
+            .where_clause_opt("status_id = ?", Some(1))
+            .where_clause_opt("name = ?", Some("bob".to_string()));
+
+        assert_eq!(
+            "select * from users where status_id = 1 and name = 'bob'",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn where_any_and_all_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_any([
+                ("email ilike ?", "%bob%".to_string()),
+                ("name ilike ?", "%bob%".to_string()),
+            ])
+            .where_all([("status_id = ?", 1), ("org_id = ?", 2)])
+            .where_any(Vec::<(String, SQLValue)>::new());
+
+        assert_eq!(
+            "select * from users where (email ilike $1 or name ilike $2) and (status_id = $3 and org_id = $4)",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn named_params_work() {
+        let q = ComposableQueryBuilder::new()
+            .table("messages")
+            .where_clause("org_id = ?", 1)
+            .param("user_id", 7)
+            .param("deleted", SQLValue::Null)
+            .where_group(|g| {
+                g.or_where_raw("sender_id = :user_id")
+                    .or_where_raw("recipient_id = :user_id")
+            })
+            .where_raw("deleted_at is not distinct from :deleted")
+            .where_raw("created_at::date > :unknown")
+            .limit(10);
+
+        assert_eq!(
+            "select * from messages where org_id = $1 and (sender_id = $2 or recipient_id = $2) and deleted_at is not distinct from null and created_at::date > :unknown limit $3",
+            q.sql()
+        );
+        assert_eq!(
+            "select * from messages where org_id = :p1 and (sender_id = :p2 or recipient_id = :p2) and deleted_at is not distinct from null and created_at::date > :unknown limit :p3",
+            q.sql_with(PlaceholderStyle::Named)
+        );
+        assert_eq!(
+            "select * from messages where org_id = 1 and (sender_id = 7 or recipient_id = 7) and deleted_at is not distinct from null and created_at::date > :unknown limit 10",
+            q.to_debug_sql()
+        );
+        assert_eq!(3, q.param_count());
+        assert_eq!(q.sql(), q.clone().into_builder().sql());
+
+        let positional = q.clone().dialect(Dialect::MySql);
+        assert_eq!(
+            "select * from messages where org_id = ? and (sender_id = ? or recipient_id = ?) and deleted_at is not distinct from null and created_at::date > :unknown limit ?",
+            positional.sql()
+        );
+        assert_eq!(4, positional.param_count());
+
+        let unused = ComposableQueryBuilder::new()
+            .table("messages")
+            .where_clause("org_id = ?", 1)
+            .param("user_id", 7);
+        assert_eq!(1, unused.param_count());
+        assert_eq!(
+            unused.param_count(),
+            unused
+                .clone()
+                .into_builder()
+                .into_sql()
+                .matches('$')
+                .count()
+        );
+    }
+
+    #[test]
+    fn where_first_works() {
+        let filters = ComposableQueryBuilder::new()
+            .where_group(|g| {
+                g.or_where("name = ?", "bob".to_string())
+                    .or_where("name = ?", "alice".to_string())
+            })
+            .where_clause("status_id = ?", 2);
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .merge(filters)
+            .where_first("org_id = ?", 1);
+
+        assert_eq!(
+            "select * from users where org_id = $1 and ((name = $2 or name = $3) and status_id = $4)",
+            q.sql()
+        );
+        assert_eq!(
+            "select * from users where org_id = 1 and ((name = 'bob' or name = 'alice') and status_id = 2)",
+            q.to_debug_sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_first("org_id = ?", 1);
+        assert_eq!("select * from users where org_id = $1", q.sql());
+    }
 
-        // Where clauses
-        let (where_str, str_values) = self.where_clause.parts();
-        str.push_str(&where_str);
-        vals.extend(str_values);
-        if !self.group_by.is_empty() {
-            str.push_str(" group by ");
-            // str.push_str("\ngroup by\n    ");
-            str.push_str(&self.group_by.join(", "));
-        }
+    #[test]
+    fn where_first_wraps_single_or_clause() {
+        let q = ComposableQueryBuilder::new()
+            .table("docs")
+            .multi_where("owner_id = ? or shared = ?", vec![5.into(), true.into()])
+            .where_first("org_id = ?", 1);
 
-        if let Some((col, dir)) = self.order_by {
-            str.push_str(" order by ");
-            str.push_str(&col);
-            str.push(' ');
-            str.push_str(dir.as_str());
-            str.push(' ');
-        }
+        assert_eq!(
+            "select * from docs where org_id = $1 and (owner_id = $2 or shared = $3)",
+            q.sql()
+        );
+    }
 
-        if let Some(limit) = self.limit {
-            str.push_str(" limit ");
-            vals.push(SQLValue::U64(limit));
-        }
+    #[test]
+    fn where_exists_works() {
+        let refunded = ComposableQueryBuilder::new()
+            .table("refunds")
+            .select("1")
+            .where_clause(
+                "refunds.order_id = orders.id and refunds.reason = ?",
+                "fraud".to_string(),
+            );
+        let shipped = ComposableQueryBuilder::new()
+            .table("shipments")
+            .select("1")
+            .where_clause(
+                "shipments.order_id = orders.id and shipments.carrier_id = ?",
+                3,
+            );
 
-        if let Some(offset) = self.offset {
-            str.push_str(" offset ");
-            vals.push(SQLValue::U64(offset));
-        }
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("org_id = ?", 1)
+            .where_not_exists(refunded)
+            .where_exists(shipped)
+            .limit(5);
 
-        (str, vals)
+        assert_eq!(
+            "select * from orders where org_id = 1 and not exists (select 1 from refunds where refunds.order_id = orders.id and refunds.reason = 'fraud') and exists (select 1 from shipments where shipments.order_id = orders.id and shipments.carrier_id = 3) limit 5",
+            q.to_debug_sql()
+        );
     }
 
-    pub fn into_builder<'args>(self) -> QueryBuilder<'args, Postgres> {
-        let mut qb: QueryBuilder<Postgres> = QueryBuilder::new("");
+    #[test]
+    fn try_limit_works() {
+        let per_page: i64 = 20;
+        let page: usize = 3;
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .try_limit(per_page)
+            .and_then(|q| q.try_offset(page * 20))
+            .unwrap();
+        assert_eq!("select * from users limit 20 offset 60", q.to_debug_sql());
 
-        let (p, v) = self.parts();
-        let parts = p.split('?');
+        let err = ComposableQueryBuilder::new()
+            .table("users")
+            .try_offset(-5i32)
+            .err();
+        assert_eq!(
+            Some(BuildError::OutOfRange {
+                clause: "offset".to_string(),
+                value: "-5".to_string()
+            }),
+            err
+        );
+    }
 
-        for pair in parts.zip_longest(v) {
-            match pair {
-                EitherOrBoth::Both(part, v) => {
-                    qb.push(part);
-                    v.push_bind(&mut qb);
-                }
-                EitherOrBoth::Left(part) => {
-                    qb.push(part);
-                }
-                EitherOrBoth::Right(v) => {
-                    v.push_bind(&mut qb);
-                }
-            }
-        }
+    #[test]
+    fn top_n_per_group_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .select_many(["id", "user_id", "total"])
+            .where_clause("org_id = ?", 7)
+            .top_n_per_group(
+                ["user_id"],
+                [("total", OrderDir::Desc), ("id", OrderDir::Asc)],
+                2,
+            )
+            .order_by("user_id", OrderDir::Asc);
 
-        qb
+        assert_eq!(
+            "select * from (select id, user_id, total, row_number() over (partition by user_id order by total desc, id asc) as rn from orders where org_id = 7) as ranked where ranked.rn <= 2 order by user_id asc",
+            q.to_debug_sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .dialect(Dialect::Sqlite)
+            .table("orders")
+            .where_clause("org_id = ?", 7)
+            .top_n_per_group(["user_id"], [("total", OrderDir::Desc)], 2);
+
+        assert_eq!(Dialect::Sqlite, q.dialect);
+        assert_eq!(
+            "select * from (select *, row_number() over (partition by user_id order by total desc) as rn from orders where org_id = ?) as ranked where ranked.rn <= ?",
+            q.sql()
+        );
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum BoolKind {
-    And,
-    Or,
-}
+    #[test]
+    fn only_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("measurements")
+            .only()
+            .select("count(*)")
+            .where_clause("logged_at > ?", "2024-01-01".to_string());
+        assert_eq!(
+            "select count(*) from only measurements where logged_at > '2024-01-01'",
+            q.to_debug_sql()
+        );
 
-impl BoolKind {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            BoolKind::And => "and",
-            BoolKind::Or => "or",
-        }
+        let q = ComposableQueryBuilder::update("measurements")
+            .only()
+            .set("archived", true)
+            .where_clause("id = ?", 1);
+        assert_eq!(
+            "update only measurements set archived = $1 where id = $2",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::delete_from("measurements")
+            .only()
+            .where_clause("id = ?", 1);
+        assert_eq!("delete from only measurements where id = $1", q.sql());
+
+        let q = ComposableQueryBuilder::new()
+            .complex_table("(?) as m", vec![ComposableQueryBuilder::new().table("a")])
+            .only();
+        assert_eq!("select * from (select * from a) as m", q.sql());
+        assert_eq!(
+            Err(BuildError::UnsupportedTableModifier {
+                modifier: "only".to_string()
+            }),
+            q.validate()
+        );
     }
-}
 
-#[cfg(test)]
-mod composable_query_builder_tests {
-    use crate::{ComposableQueryBuilder, OrderDir};
+    #[test]
+    fn tablesample_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("events")
+            .select("count(*)")
+            .tablesample_repeatable(SampleMethod::Bernoulli, 2.5, 42)
+            .join("inner join users on users.id = events.user_id")
+            .where_clause("events.kind = ?", "click".to_string());
+
+        assert_eq!(
+            "select count(*) from events tablesample bernoulli ($1::real) repeatable ($2) inner join users on users.id = events.user_id where events.kind = $3",
+            q.sql()
+        );
+        assert_eq!(
+            "select count(*) from events tablesample bernoulli (2.5::real) repeatable (42) inner join users on users.id = events.user_id where events.kind = 'click'",
+            q.to_debug_sql()
+        );
+        assert_eq!(3, q.param_count());
+        assert_eq!(Ok(()), q.validate());
+
+        let q = ComposableQueryBuilder::delete_from("events")
+            .tablesample(SampleMethod::System, 10.0)
+            .where_clause("id = ?", 1);
+        assert_eq!(
+            Err(BuildError::UnsupportedTableModifier {
+                modifier: "tablesample".to_string()
+            }),
+            q.validate()
+        );
+    }
 
     #[test]
-    fn or_where_works() {
+    fn where_with_works() {
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .or_where("status_id = ?", 1)
-            .or_where("status_id = ?", 2)
-            .or_where("status_id = ?", 3)
-            .into_builder();
-        let query = q.sql();
+            .where_with("org_id = ?", 1, BoolKind::And)
+            .where_with("status_id = ?", 2, BoolKind::And);
+        assert_eq!(
+            ComposableQueryBuilder::new()
+                .table("users")
+                .where_clause("org_id = ?", 1)
+                .where_clause("status_id = ?", 2)
+                .sql(),
+            q.sql()
+        );
 
+        let any_email = ["a@example.com", "b@example.com"]
+            .into_iter()
+            .fold(ComposableQueryBuilder::new().table("users"), |q, email| {
+                q.where_with("email = ?", email.to_string(), BoolKind::Or)
+            });
         assert_eq!(
-            "select * from users where status_id = $1 or status_id = $2 or status_id = $3",
-            query
+            "select * from users where email = 'a@example.com' or email = 'b@example.com'",
+            any_email.to_debug_sql()
         );
     }
 
     #[test]
-    fn limit_works() {
+    fn bool_kind_joins_to_previous_clause() {
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .limit(10)
-            .into_builder();
-        let query = q.sql();
+            .where_clause("a = ?", 1)
+            .or_where("b = ?", 2);
+        assert_eq!("select * from users where a = 1 or b = 2", q.to_debug_sql());
 
-        assert_eq!("select * from users limit $1", query);
+        // The first clause's kind is ignored
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .or_where("a = ?", 1)
+            .where_clause("b = ?", 2)
+            .or_where("c = ?", 3);
+        assert_eq!(
+            "select * from users where a = 1 and b = 2 or c = 3",
+            q.to_debug_sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("a = ?", 1)
+            .or_multi_where("(b = ? or c = ?)", vec![2.into(), 3.into()])
+            .multi_where("d = ?", vec![4.into()]);
+        assert_eq!(
+            "select * from users where a = 1 or (b = 2 or c = 3) and d = 4",
+            q.to_debug_sql()
+        );
     }
 
     #[test]
-    fn limit_opt_works() {
+    fn str_values_work() {
+        let email = "bob@example.com".to_string();
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .limit_opt(Some(10))
-            .into_builder();
-        let query = q.sql();
+            .where_clause("name = ?", "O'Brien")
+            .where_clause("email = ?", &email)
+            .where_in("role", vec!["admin", "owner"]);
 
-        assert_eq!("select * from users limit $1", query);
+        assert_eq!(
+            "select * from users where name = 'O''Brien' and email = 'bob@example.com' and role in ('admin', 'owner')",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn numeric_from_works() {
+        assert_eq!(SQLValue::I16(-8), (-8i8).into());
+        assert_eq!(SQLValue::I16(255), 255u8.into());
+        assert_eq!(SQLValue::I32(65535), 65535u16.into());
+        assert_eq!(SQLValue::I64(4294967295), u32::MAX.into());
+        assert_eq!(SQLValue::F64(1.5), 1.5f32.into());
 
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .limit_opt(None)
+            .where_clause("age > ?", 18u8)
+            .where_clause("score >= ?", 0.25f32);
+        assert_eq!(
+            "select * from users where age > 18 and score >= 0.25",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn u64_overflow_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("accounts")
+            .where_clause("external_id = ?", i64::MAX as u64);
+        assert_eq!(Ok(()), q.validate());
+        assert!(q.try_into_builder().is_ok());
+
+        let q = ComposableQueryBuilder::new()
+            .table("accounts")
+            .where_clause("external_id = ?", u64::MAX);
+        assert_eq!(
+            Err(BuildError::U64Overflow { value: u64::MAX }),
+            q.validate()
+        );
+        assert!(matches!(
+            q.clone().try_into_builder(),
+            Err(BuildError::U64Overflow { value: u64::MAX })
+        ));
+        assert_eq!(
+            "select * from accounts where external_id = 18446744073709551615",
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "too large to bind as an i64")]
+    fn u64_overflow_panics_in_into_builder() {
+        ComposableQueryBuilder::new()
+            .table("accounts")
+            .where_clause("external_id = ?", u64::MAX)
             .into_builder();
-        let query = q.sql();
+    }
 
-        assert_eq!("select * from users", query);
+    #[test]
+    fn into_bind_works() {
+        let mut qb: QueryBuilder<Postgres> =
+            QueryBuilder::new("select * from users where id = any(");
+        SQLValue::VecI64((0..10_000).collect())
+            .into_bind(&mut qb)
+            .unwrap();
+        qb.push(") and email = ");
+        SQLValue::from("bob@example.com")
+            .push_bind(&mut qb)
+            .unwrap();
+
+        assert_eq!(
+            "select * from users where id = any($1) and email = $2",
+            qb.sql()
+        );
+
+        qb.push(" and external_id = ");
+        assert_eq!(
+            Err(BuildError::U64Overflow { value: u64::MAX }),
+            SQLValue::U64(u64::MAX).into_bind(&mut qb)
+        );
+        assert!(qb.sql().ends_with("external_id = "));
     }
 
     #[test]
-    fn offset_works() {
+    fn with_capacity_works() {
+        let build = |qb: ComposableQueryBuilder| {
+            (0..20).fold(
+                qb.table("users")
+                    .select("id")
+                    .join("inner join orgs on orgs.id = users.org_id"),
+                |q, i| q.where_clause("status_id <> ?", i),
+            )
+        };
+
+        let q = build(ComposableQueryBuilder::with_capacity(20, 1, 1));
+        assert!(q == build(ComposableQueryBuilder::new()));
+        assert_eq!(
+            build(ComposableQueryBuilder::new()).to_debug_sql(),
+            q.to_debug_sql()
+        );
+    }
+
+    #[test]
+    fn where_each_works() {
+        let filters: Vec<(String, SQLValue)> = vec![
+            ("status_id = ?".to_string(), 2.into()),
+            ("created_at > ?".to_string(), "2024-01-01".into()),
+        ];
+        let base = ComposableQueryBuilder::new()
+            .table("users")
+            .or_where("org_id = ?", 1)
+            .or_where("org_id = ?", 2);
+
+        let q = base.merge(ComposableQueryBuilder::new().where_each(filters));
+        assert_eq!(
+            "select * from users where (org_id = 1 or org_id = 2) and (status_id = 2 and created_at > '2024-01-01')",
+            q.to_debug_sql()
+        );
+
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .offset(10)
-            .into_builder();
-        let query = q.sql();
+            .where_each(Vec::<(String, SQLValue)>::new());
+        assert_eq!("select * from users", q.to_debug_sql());
+    }
 
-        assert_eq!("select * from users offset $1", query);
+    #[test]
+    fn select_coalesce_works() {
+        let q = ComposableQueryBuilder::new()
+            .table("campaigns")
+            .select("id")
+            .select_coalesce("nickname", "coalesce(name, 'unnamed')", "label")
+            .select_coalesce("spend / nullif(clicks, 0)", "0", "cpc")
+            .select_nullif("trim(notes)", "''", "notes");
+
+        assert_eq!(
+            "select id, coalesce(nickname, coalesce(name, 'unnamed')) as label, coalesce(spend / nullif(clicks, 0), 0) as cpc, nullif(trim(notes), '') as notes from campaigns",
+            q.sql()
+        );
     }
 
     #[test]
-    fn offset_opt_works() {
+    fn case_builder_works() {
+        let tier = CaseBuilder::new()
+            .when_bind("total >= ?", 1000, "gold")
+            .when_bind("total >= ?", 100, "silver")
+            .when("total is null", "none");
         let q = ComposableQueryBuilder::new()
-            .table("users")
-            .offset_opt(Some(10))
-            .into_builder();
-        let query = q.sql();
+            .table("orders")
+            .select("id")
+            .select_case(tier, "tier")
+            .select_case(
+                CaseBuilder::new().when("refunded", true).else_(false),
+                "flag",
+            )
+            .where_clause("user_id = ?", 1);
 
-        assert_eq!("select * from users offset $1", query);
+        assert_eq!(
+            "select id, case when total >= 1000 then 'gold' when total >= 100 then 'silver' when total is null then 'none' end as tier, case when refunded then true else false end as flag from orders where user_id = 1",
+            q.to_debug_sql()
+        );
+        assert_eq!(8, q.param_count());
+        assert_eq!(Ok(()), q.validate());
+    }
+
+    #[test]
+    fn first_and_last_work() {
+        let base = ComposableQueryBuilder::new()
+            .table("orders")
+            .where_clause("user_id = ?", 1);
+
+        assert_eq!(
+            "select * from orders where user_id = 1 order by created_at desc limit 1",
+            base.clone()
+                .first("created_at", OrderDir::Desc)
+                .to_debug_sql()
+        );
+        assert_eq!(
+            "select * from orders where user_id = 1 order by id desc limit 1",
+            base.last("id", OrderDir::Asc).to_debug_sql()
+        );
+        assert_eq!(OrderDir::Asc, OrderDir::Desc.reverse());
+    }
 
+    #[test]
+    fn group_by_rollup_works() {
         let q = ComposableQueryBuilder::new()
-            .table("users")
-            .offset_opt(None)
-            .into_builder();
-        let query = q.sql();
+            .table("sales")
+            .select_many(["year", "region", "product", "sum(total)"])
+            .where_clause("org_id = ?", 1)
+            .group_by("year")
+            .group_by_rollup(["region", "city"])
+            .group_by_cube(["product", "channel"])
+            .having("sum(total) > ?", 0);
 
-        assert_eq!("select * from users", query);
+        assert_eq!(
+            "select year, region, product, sum(total) from sales where org_id = 1 group by year, rollup (region, city), cube (product, channel) having sum(total) > 0",
+            q.to_debug_sql()
+        );
     }
 
     #[test]
-    fn order_by_works() {
+    fn grouping_sets_works() {
         let q = ComposableQueryBuilder::new()
-            .table("users")
-            .order_by("email", OrderDir::Desc)
-            .into_builder();
-        let query = q.sql();
+            .table("sales")
+            .select_many(["year", "region", "sum(total)"])
+            .group_by("year")
+            .grouping_sets([vec!["region".to_string()], vec![]]);
+        assert_eq!(
+            "select year, region, sum(total) from sales group by year, grouping sets ((region), ())",
+            q.sql()
+        );
 
-        assert_eq!("select * from users order by email desc ", query);
+        let q = ComposableQueryBuilder::new()
+            .table("sales")
+            .select("sum(total)")
+            .grouping_sets([Vec::<String>::new()]);
+        assert_eq!(
+            "select sum(total) from sales group by grouping sets (())",
+            q.sql()
+        );
+    }
 
+    #[test]
+    fn explain_works() {
         let q = ComposableQueryBuilder::new()
-            .table("users")
-            .order_by("email", OrderDir::Asc)
-            .into_builder();
-        let query = q.sql();
+            .with(
+                "recent",
+                ComposableQueryBuilder::new()
+                    .table("orders")
+                    .where_clause("created_at > ?", "2024-01-01"),
+            )
+            .table("recent")
+            .where_clause("total > ?", 100)
+            .explain_analyze();
+
+        assert_eq!(
+            "explain (analyze, format json) with recent as (select * from orders where created_at > $1) select * from recent where total > $2",
+            q.sql()
+        );
+
+        let q = ComposableQueryBuilder::delete_from("sessions")
+            .where_raw("expires_at < now()")
+            .explain();
+        assert_eq!(
+            "explain (format json) delete from sessions where expires_at < now()",
+            q.sql()
+        );
+    }
+
+    #[test]
+    fn getters_work() {
+        let q = ComposableQueryBuilder::new().table("users");
+        assert_eq!(0, q.selects().len());
+        assert_eq!(0, q.joins().len());
+        assert_eq!(0, q.where_count());
 
-        assert_eq!("select * from users order by email asc ", query);
+        let q = q
+            .select("users.id")
+            .select_as("count(*)", "n")
+            .left_join("orders", "orders.user_id = users.id")
+            .where_clause("users.org_id = ?", 1)
+            .multi_where("users.id in (?, ?)", vec![2.into(), 3.into()])
+            .where_any([("users.status_id = ?", 1), ("users.status_id = ?", 2)])
+            .group_by("users.id")
+            .having("count(*) > ?", 5);
+
+        assert_eq!(
+            vec!["users.id", "count(*) as n"],
+            q.selects().collect::<Vec<_>>()
+        );
+        assert_eq!(1, q.joins().len());
+        assert_eq!(3, q.where_count());
+        assert_eq!(1, q.having_count());
     }
 
     #[test]
-    fn multi_where_works() {
+    fn interleaved_multi_where_keeps_order() {
         let q = ComposableQueryBuilder::new()
             .table("users")
-            .multi_where(
-                "(orders > ? and orders < ?) or sales > ?",
-                vec![10.into(), 100.into(), 123.45.into()],
-            )
-            .into_builder();
-        let query = q.sql();
+            .multi_where("(x = ? or y = ?)", vec![1.into(), 2.into()])
+            .or_where("z = ?", 3);
+        assert_eq!(
+            "select * from users where (x = $1 or y = $2) or z = $3",
+            q.sql()
+        );
 
+        let q = ComposableQueryBuilder::new()
+            .table("users")
+            .where_clause("a = ?", 1)
+            .or_multi_where("b in (?, ?)", vec![2.into(), 3.into()])
+            .where_clause("c = ?", 4);
         assert_eq!(
-            "select * from users where (orders > $1 and orders < $2) or sales > $3",
-            query
+            "select * from users where a = 1 or b in (2, 3) and c = 4",
+            q.to_debug_sql()
+        );
+
+        let q = ComposableQueryBuilder::new()
+            .table("orders")
+            .group_by("user_id")
+            .having_multi("sum(total) between ? and ?", vec![10.into(), 20.into()])
+            .having("count(*) > ?", 1);
+        assert_eq!(
+            "select * from orders group by user_id having sum(total) between 10 and 20 and count(*) > 1",
+            q.to_debug_sql()
         );
     }
 }
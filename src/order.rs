@@ -1,4 +1,8 @@
-#[derive(Clone, Copy, PartialEq)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum OrderDir {
     Asc,
@@ -12,13 +16,64 @@ impl OrderDir {
             OrderDir::Desc => "desc",
         }
     }
+
+    /// The opposite direction.
+    pub fn reverse(&self) -> OrderDir {
+        match self {
+            OrderDir::Asc => OrderDir::Desc,
+            OrderDir::Desc => OrderDir::Asc,
+        }
+    }
 }
 
-impl ToString for OrderDir {
-    fn to_string(&self) -> String {
+impl fmt::Display for OrderDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Where nulls are placed in an order by, ie `nulls first` or `nulls last`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+impl NullsOrder {
+    pub fn as_str(&self) -> &'static str {
         match self {
-            OrderDir::Asc => "asc".to_string(),
-            OrderDir::Desc => "desc".to_string(),
+            NullsOrder::First => "nulls first",
+            NullsOrder::Last => "nulls last",
+        }
+    }
+}
+
+/// Returned when parsing an [OrderDir] from anything other than `asc` or `desc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseOrderDirError(String);
+
+impl fmt::Display for ParseOrderDirError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid order direction `{}`, expected `asc` or `desc`",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for ParseOrderDirError {}
+
+/// Parses `asc` or `desc`, ignoring case and surrounding whitespace.
+impl FromStr for OrderDir {
+    type Err = ParseOrderDirError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "asc" => Ok(OrderDir::Asc),
+            "desc" => Ok(OrderDir::Desc),
+            _ => Err(ParseOrderDirError(s.to_string())),
         }
     }
 }
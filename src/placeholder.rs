@@ -0,0 +1,277 @@
+use itertools::{EitherOrBoth, Itertools};
+
+use crate::error::BuildError;
+use crate::sql_value::SQLValue;
+
+/// How placeholders are written in rendered sql.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlaceholderStyle {
+    /// Postgres style `$1`, `$2`, ...
+    Numbered,
+    /// `?` for every value, as used by MySQL and SQLite
+    Positional,
+    /// Named `:p1`, `:p2`, ... placeholders
+    Named,
+}
+
+/// A piece of a rendered query, either raw sql or a value to bind in place of a `?`.
+pub enum Segment<'a> {
+    Sql(&'a str),
+    Value(SQLValue),
+    /// A reference to a named param. `reuse` is the number of the placeholder the param was
+    /// first bound as, for every reference after the first.
+    Param {
+        value: SQLValue,
+        reuse: Option<usize>,
+    },
+}
+
+/// Splits `sql` on its `?` placeholders, returning the pieces of sql between each one. A `??`
+/// is an escaped literal `?`, such as the jsonb `?` operator, and doesn't split. When
+/// `unescape` is true it's written as a single `?`, otherwise it's kept as `??` so the sql can
+/// be split again later.
+pub fn split(sql: &str, unescape: bool) -> Vec<Vec<&str>> {
+    let mut out = vec![];
+    let mut part = vec![];
+    let mut start = 0;
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c != '?' {
+            continue;
+        }
+
+        if let Some((j, '?')) = chars.peek().copied() {
+            chars.next();
+            let end = if unescape { j } else { j + 1 };
+            part.push(&sql[start..end]);
+            start = j + 1;
+            continue;
+        }
+
+        part.push(&sql[start..i]);
+        out.push(std::mem::take(&mut part));
+        start = i + 1;
+    }
+    part.push(&sql[start..]);
+    out.push(part);
+
+    out
+}
+
+/// Splits `sql` on its `?` placeholders and interleaves the given values, in order. Escaped
+/// `??` placeholders are written as a literal `?`.
+pub fn segments(sql: &str, values: Vec<SQLValue>) -> Vec<Segment<'_>> {
+    let mut out = vec![];
+
+    for pair in split(sql, true).into_iter().zip_longest(values) {
+        match pair {
+            EitherOrBoth::Both(parts, v) => {
+                out.extend(parts.into_iter().map(Segment::Sql));
+                out.push(Segment::Value(v));
+            }
+            EitherOrBoth::Left(parts) => out.extend(parts.into_iter().map(Segment::Sql)),
+            EitherOrBoth::Right(v) => out.push(Segment::Value(v)),
+        }
+    }
+
+    out
+}
+
+/// Counts the `?` placeholders in `sql`, ignoring escaped `??`.
+pub fn count(sql: &str) -> usize {
+    split(sql, false).len() - 1
+}
+
+/// Errors if the number of `?` placeholders in `sql` doesn't match the number of values.
+pub fn check(sql: &str, values: &[SQLValue]) -> Result<(), BuildError> {
+    let placeholders = count(sql);
+    if placeholders != values.len() {
+        return Err(BuildError::PlaceholderMismatch {
+            placeholders,
+            values: values.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Like [segments], but also splits out `:name` references to the given named params. The
+/// first reference to a param binds it, later references reuse its placeholder number. `::`
+/// casts, names that aren't params and anything inside a quoted identifier are left alone.
+pub fn segments_with_params<'a>(
+    sql: &'a str,
+    values: Vec<SQLValue>,
+    params: &[(String, SQLValue)],
+) -> Vec<Segment<'a>> {
+    let mut out = vec![];
+    let mut bound = 0;
+    let mut numbers: Vec<Option<usize>> = vec![None; params.len()];
+    // A quoted identifier can span several pieces when it contains an escaped `??`
+    let mut quote = None;
+
+    for segment in segments(sql, values) {
+        match segment {
+            Segment::Sql(s) => {
+                let mut start = 0;
+                for (ref_start, ref_end, i) in param_refs(s, params, &mut quote) {
+                    out.push(Segment::Sql(&s[start..ref_start]));
+
+                    let value = params[i].1.clone();
+                    let reuse = numbers[i];
+                    if reuse.is_none() && value.is_bound() {
+                        bound += 1;
+                        numbers[i] = Some(bound);
+                    }
+                    out.push(Segment::Param { value, reuse });
+
+                    start = ref_end;
+                }
+                out.push(Segment::Sql(&s[start..]));
+            }
+            Segment::Value(v) => {
+                if v.is_bound() {
+                    bound += 1;
+                }
+                out.push(Segment::Value(v));
+            }
+            param => out.push(param),
+        }
+    }
+
+    out
+}
+
+/// Counts the values bound for references to the given named params in `sql`. Numbered
+/// placeholders bind each referenced param once and reuse it, positional ones bind it for
+/// every reference. Null params are written as a literal and aren't counted.
+pub fn count_param_binds(
+    sql: &str,
+    params: &[(String, SQLValue)],
+    style: PlaceholderStyle,
+) -> usize {
+    let mut count = 0;
+    let mut seen = vec![false; params.len()];
+    let mut quote = None;
+
+    // Walks the same pieces as [segments_with_params], so quoted identifiers line up
+    for part in split(sql, true).into_iter().flatten() {
+        for (_, _, i) in param_refs(part, params, &mut quote) {
+            let reused = style != PlaceholderStyle::Positional && seen[i];
+            if params[i].1.is_bound() && !reused {
+                count += 1;
+            }
+            seen[i] = true;
+        }
+    }
+
+    count
+}
+
+/// Finds the `:name` references to the given params, returning their start and end offsets
+/// along with the index of the param. `quote` is the quote character of the identifier `sql`
+/// starts inside of, if any, and is updated to the one it ends inside of.
+fn param_refs(
+    sql: &str,
+    params: &[(String, SQLValue)],
+    quote: &mut Option<u8>,
+) -> Vec<(usize, usize, usize)> {
+    let mut out = vec![];
+    if params.is_empty() {
+        return out;
+    }
+
+    let bytes = sql.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(q) = *quote {
+            if bytes[i] == q {
+                *quote = None;
+            }
+            i += 1;
+            continue;
+        }
+        if bytes[i] == b'"' || bytes[i] == b'`' {
+            *quote = Some(bytes[i]);
+            i += 1;
+            continue;
+        }
+        if bytes[i] != b':' {
+            i += 1;
+            continue;
+        }
+
+        // Skip `::` casts
+        if bytes.get(i + 1) == Some(&b':') {
+            i += 2;
+            continue;
+        }
+
+        let mut end = i + 1;
+        while end < bytes.len() && is_ident(bytes[end]) {
+            end += 1;
+        }
+
+        let name = &sql[i + 1..end];
+        if let Some(index) = params.iter().position(|(n, _)| n == name) {
+            out.push((i, end, index));
+        }
+        i = end.max(i + 1);
+    }
+
+    out
+}
+
+/// Replaces each `?` placeholder with the given style. Null values are written as a literal
+/// `null`, matching what sqlx's `QueryBuilder` ends up with.
+/// Named params are written the same way, reusing the number they were first bound as.
+pub fn render(
+    sql: &str,
+    values: Vec<SQLValue>,
+    params: &[(String, SQLValue)],
+    style: PlaceholderStyle,
+) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut n = 0;
+
+    for segment in segments_with_params(sql, values, params) {
+        let number = match segment {
+            Segment::Sql(s) => {
+                out.push_str(s);
+                continue;
+            }
+            Segment::Value(SQLValue::Null)
+            | Segment::Param {
+                value: SQLValue::Null,
+                ..
+            } => {
+                out.push_str("null");
+                continue;
+            }
+            Segment::Param {
+                reuse: Some(number),
+                ..
+            } => number,
+            Segment::Value(_) | Segment::Param { reuse: None, .. } => {
+                n += 1;
+                n
+            }
+        };
+
+        match style {
+            PlaceholderStyle::Numbered => {
+                out.push('$');
+                out.push_str(&number.to_string());
+            }
+            PlaceholderStyle::Positional => out.push('?'),
+            PlaceholderStyle::Named => {
+                out.push_str(":p");
+                out.push_str(&number.to_string());
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,45 @@
+use itertools::Itertools;
+
+use crate::sql_value::SQLValue;
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetClauses {
+    clauses: Vec<(String, SQLValue)>,
+}
+
+impl SetClauses {
+    pub fn new() -> Self {
+        Self { clauses: vec![] }
+    }
+
+    pub fn push(&mut self, column: impl Into<String>, value: impl Into<SQLValue>) {
+        self.clauses.push((column.into(), value.into()));
+    }
+
+    pub fn extend(&mut self, other: SetClauses) {
+        self.clauses.extend(other.clauses);
+    }
+
+    /// The number of values that will be bound.
+    pub fn param_count(&self) -> usize {
+        self.clauses.iter().filter(|(_, v)| v.is_bound()).count()
+    }
+
+    pub fn parts(self) -> (String, Vec<SQLValue>) {
+        if self.clauses.is_empty() {
+            return ("".to_string(), vec![]);
+        }
+
+        let mut out = " set ".to_string();
+        out.push_str(
+            &self
+                .clauses
+                .iter()
+                .map(|(column, _)| format!("{} = ?", column))
+                .join(", "),
+        );
+
+        (out, self.clauses.into_iter().map(|(_, v)| v).collect())
+    }
+}
@@ -1,6 +1,15 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use itertools::Itertools;
+#[cfg(feature = "mysql")]
+use sqlx::MySql;
+#[cfg(feature = "sqlite")]
+use sqlx::Sqlite;
 use sqlx::{Postgres, QueryBuilder};
 
+#[cfg(feature = "mysql")]
+use crate::dialect::Dialect;
+use crate::error::BuildError;
+
 /// SQLValue is an enum wrapper around the various types that can be bound to a query.
 ///
 /// This allows us to do some fairly magic looking things with the query builder, in
@@ -18,7 +27,11 @@ use sqlx::{Postgres, QueryBuilder};
 /// let sql = query.sql();
 /// assert_eq!("select * from users where status_id = $1 and email = $2", sql);
 /// ```
-#[derive(Debug, Clone)]
+///
+/// Values compare equal when they're the same variant holding equal values. `F64` uses float
+/// equality, so `NaN` is never equal to itself and `0.0` equals `-0.0`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SQLValue {
     I16(i16),
     I32(i32),
@@ -26,24 +39,208 @@ pub enum SQLValue {
     U64(u64),
     F64(f64),
     DateTime(NaiveDateTime),
+    Date(NaiveDate),
+    DateTimeTz(DateTime<Utc>),
+    VecI32(Vec<i32>),
     VecI64(Vec<i64>),
+    VecString(Vec<String>),
+    /// Bound as `bytea`.
+    Bytes(Vec<u8>),
     String(String),
     Bool(bool),
+    #[cfg(feature = "uuid")]
+    Uuid(uuid::Uuid),
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+    /// Bound as `jsonb`.
+    #[cfg(feature = "json")]
+    Json(serde_json::Value),
+    /// An untyped sql `null`. This is pushed as a literal rather than bound, so it can be
+    /// used against a column of any type.
+    Null,
 }
 
 impl SQLValue {
-    pub fn push_bind(&self, qb: &mut QueryBuilder<Postgres>) {
+    /// Whether the value is bound as a parameter. `Null` is written as a literal `null`
+    /// instead.
+    pub fn is_bound(&self) -> bool {
+        !matches!(self, SQLValue::Null)
+    }
+
+    /// Binds the value to a Postgres query, moving it rather than cloning. `U64` is bound as a
+    /// `bigint`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [BuildError::U64Overflow] without binding anything when a `U64` is above
+    /// `i64::MAX`, rather than binding a wrapped negative number.
+    pub fn into_bind(self, qb: &mut QueryBuilder<Postgres>) -> Result<(), BuildError> {
         match self {
-            SQLValue::I16(v) => qb.push_bind(*v),
-            SQLValue::I32(v) => qb.push_bind(*v),
-            SQLValue::I64(v) => qb.push_bind(*v),
-            SQLValue::U64(v) => qb.push_bind(*v as i64),
-            SQLValue::F64(v) => qb.push_bind(*v),
-            SQLValue::DateTime(v) => qb.push_bind(*v),
-            SQLValue::VecI64(v) => qb.push_bind(v.clone()),
-            SQLValue::String(v) => qb.push_bind(v.clone()),
-            SQLValue::Bool(v) => qb.push_bind(*v),
+            SQLValue::I16(v) => qb.push_bind(v),
+            SQLValue::I32(v) => qb.push_bind(v),
+            SQLValue::I64(v) => qb.push_bind(v),
+            SQLValue::U64(v) => qb.push_bind(u64_to_i64(v)?),
+            SQLValue::F64(v) => qb.push_bind(v),
+            SQLValue::DateTime(v) => qb.push_bind(v),
+            SQLValue::Date(v) => qb.push_bind(v),
+            SQLValue::DateTimeTz(v) => qb.push_bind(v),
+            SQLValue::VecI32(v) => qb.push_bind(v),
+            SQLValue::VecI64(v) => qb.push_bind(v),
+            SQLValue::VecString(v) => qb.push_bind(v),
+            SQLValue::Bytes(v) => qb.push_bind(v),
+            SQLValue::String(v) => qb.push_bind(v),
+            SQLValue::Bool(v) => qb.push_bind(v),
+            #[cfg(feature = "uuid")]
+            SQLValue::Uuid(v) => qb.push_bind(v),
+            #[cfg(feature = "decimal")]
+            SQLValue::Decimal(v) => qb.push_bind(v),
+            #[cfg(feature = "json")]
+            SQLValue::Json(v) => qb.push_bind(v),
+            SQLValue::Null => qb.push("null"),
         };
+
+        Ok(())
+    }
+
+    /// Like [into_bind](SQLValue::into_bind), binding a clone of the value.
+    pub fn push_bind(&self, qb: &mut QueryBuilder<Postgres>) -> Result<(), BuildError> {
+        self.clone().into_bind(qb)
+    }
+
+    /// Serializes any [serde::Serialize] type into a [SQLValue::Json].
+    ///
+    /// ```rust
+    /// use composable_query_builder::{ComposableQueryBuilder, SQLValue};
+    /// let filter = std::collections::HashMap::from([("theme", "dark")]);
+    /// let query = ComposableQueryBuilder::new()
+    ///     .table("users")
+    ///     .where_clause("settings @> ?", SQLValue::json(&filter).unwrap())
+    ///     .into_builder();
+    ///
+    /// let sql = query.sql();
+    /// assert_eq!("select * from users where settings @> $1", sql);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json(v: &impl serde::Serialize) -> Result<Self, serde_json::Error> {
+        Ok(SQLValue::Json(serde_json::to_value(v)?))
+    }
+
+    /// Binds the value to a MySQL query. MySQL has no array type, so the `Vec*` variants
+    /// (other than [SQLValue::Bytes]) are not supported.
+    ///
+    /// # Errors
+    ///
+    /// Returns [BuildError::UnsupportedArray] without binding anything for an array value.
+    #[cfg(feature = "mysql")]
+    pub fn into_bind_mysql(self, qb: &mut QueryBuilder<MySql>) -> Result<(), BuildError> {
+        match self {
+            SQLValue::I16(v) => qb.push_bind(v),
+            SQLValue::I32(v) => qb.push_bind(v),
+            SQLValue::I64(v) => qb.push_bind(v),
+            SQLValue::U64(v) => qb.push_bind(v),
+            SQLValue::F64(v) => qb.push_bind(v),
+            SQLValue::DateTime(v) => qb.push_bind(v),
+            SQLValue::Date(v) => qb.push_bind(v),
+            SQLValue::DateTimeTz(v) => qb.push_bind(v),
+            SQLValue::VecI32(_) | SQLValue::VecI64(_) | SQLValue::VecString(_) => {
+                return Err(BuildError::UnsupportedArray {
+                    dialect: Dialect::MySql,
+                });
+            }
+            SQLValue::Bytes(v) => qb.push_bind(v),
+            SQLValue::String(v) => qb.push_bind(v),
+            SQLValue::Bool(v) => qb.push_bind(v),
+            #[cfg(feature = "uuid")]
+            SQLValue::Uuid(v) => qb.push_bind(v),
+            #[cfg(feature = "decimal")]
+            SQLValue::Decimal(v) => qb.push_bind(v),
+            #[cfg(feature = "json")]
+            SQLValue::Json(v) => qb.push_bind(v),
+            SQLValue::Null => qb.push("null"),
+        };
+
+        Ok(())
+    }
+
+    /// Like [into_bind_mysql](SQLValue::into_bind_mysql), binding a clone of the value.
+    #[cfg(feature = "mysql")]
+    pub fn push_bind_mysql(&self, qb: &mut QueryBuilder<MySql>) -> Result<(), BuildError> {
+        self.clone().into_bind_mysql(qb)
+    }
+
+    /// Binds the value to a SQLite query. SQLite has no array or decimal types, so these fall
+    /// back to text:
+    ///
+    /// - the `Vec*` variants (other than [SQLValue::Bytes]) are bound as a json array, ie
+    ///   `[1,2,3]`, which can be expanded with `json_each(?)`
+    /// - `Decimal` is bound as its string representation
+    /// - `U64` is bound as an `i64`, erroring above `i64::MAX` like
+    ///   [into_bind](SQLValue::into_bind)
+    #[cfg(feature = "sqlite")]
+    pub fn into_bind_sqlite(self, qb: &mut QueryBuilder<Sqlite>) -> Result<(), BuildError> {
+        match self {
+            SQLValue::I16(v) => qb.push_bind(v),
+            SQLValue::I32(v) => qb.push_bind(v),
+            SQLValue::I64(v) => qb.push_bind(v),
+            SQLValue::U64(v) => qb.push_bind(u64_to_i64(v)?),
+            SQLValue::F64(v) => qb.push_bind(v),
+            SQLValue::DateTime(v) => qb.push_bind(v),
+            SQLValue::Date(v) => qb.push_bind(v),
+            SQLValue::DateTimeTz(v) => qb.push_bind(v),
+            SQLValue::VecI32(v) => qb.push_bind(format!("[{}]", v.iter().join(","))),
+            SQLValue::VecI64(v) => qb.push_bind(format!("[{}]", v.iter().join(","))),
+            SQLValue::VecString(v) => {
+                qb.push_bind(format!("[{}]", v.iter().map(|s| json_string(s)).join(",")))
+            }
+            SQLValue::Bytes(v) => qb.push_bind(v),
+            SQLValue::String(v) => qb.push_bind(v),
+            SQLValue::Bool(v) => qb.push_bind(v),
+            #[cfg(feature = "uuid")]
+            SQLValue::Uuid(v) => qb.push_bind(v),
+            #[cfg(feature = "decimal")]
+            SQLValue::Decimal(v) => qb.push_bind(v.to_string()),
+            #[cfg(feature = "json")]
+            SQLValue::Json(v) => qb.push_bind(v),
+            SQLValue::Null => qb.push("null"),
+        };
+
+        Ok(())
+    }
+
+    /// Like [into_bind_sqlite](SQLValue::into_bind_sqlite), binding a clone of the value.
+    #[cfg(feature = "sqlite")]
+    pub fn push_bind_sqlite(&self, qb: &mut QueryBuilder<Sqlite>) -> Result<(), BuildError> {
+        self.clone().into_bind_sqlite(qb)
+    }
+
+    /// Renders the value as a sql literal, for debugging only. The output is not escaped
+    /// thoroughly enough to be executed and must never be used to build real queries.
+    pub fn to_debug_sql(&self) -> String {
+        match self {
+            SQLValue::I16(v) => v.to_string(),
+            SQLValue::I32(v) => v.to_string(),
+            SQLValue::I64(v) => v.to_string(),
+            SQLValue::U64(v) => v.to_string(),
+            SQLValue::F64(v) => v.to_string(),
+            SQLValue::DateTime(v) => quote(&v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            SQLValue::Date(v) => quote(&v.format("%Y-%m-%d").to_string()),
+            SQLValue::DateTimeTz(v) => quote(&v.to_rfc3339()),
+            SQLValue::VecI32(v) => format!("array[{}]", v.iter().join(", ")),
+            SQLValue::VecI64(v) => format!("array[{}]", v.iter().join(", ")),
+            SQLValue::VecString(v) => format!("array[{}]", v.iter().map(|s| quote(s)).join(", ")),
+            SQLValue::Bytes(v) => {
+                format!("'\\x{}'", v.iter().map(|b| format!("{:02x}", b)).join(""))
+            }
+            SQLValue::String(v) => quote(v),
+            SQLValue::Bool(v) => v.to_string(),
+            #[cfg(feature = "uuid")]
+            SQLValue::Uuid(v) => quote(&v.to_string()),
+            #[cfg(feature = "decimal")]
+            SQLValue::Decimal(v) => v.to_string(),
+            #[cfg(feature = "json")]
+            SQLValue::Json(v) => quote(&v.to_string()),
+            SQLValue::Null => "null".to_string(),
+        }
     }
 
     /// This method isn't actually used, but is here to enable a compile time check
@@ -57,11 +254,94 @@ impl SQLValue {
             SQLValue::U64(v) => v.into(),
             SQLValue::F64(v) => v.into(),
             SQLValue::DateTime(v) => v.into(),
+            SQLValue::Date(v) => v.into(),
+            SQLValue::DateTimeTz(v) => v.into(),
+            SQLValue::VecI32(v) => v.into(),
             SQLValue::VecI64(v) => v.into(),
+            SQLValue::VecString(v) => v.into(),
+            SQLValue::Bytes(v) => v.into(),
             SQLValue::String(v) => v.into(),
             SQLValue::Bool(v) => v.into(),
+            #[cfg(feature = "uuid")]
+            SQLValue::Uuid(v) => v.into(),
+            #[cfg(feature = "decimal")]
+            SQLValue::Decimal(v) => v.into(),
+            #[cfg(feature = "json")]
+            SQLValue::Json(v) => v.into(),
+            SQLValue::Null => None::<i32>.into(),
+        }
+    }
+}
+
+fn u64_to_i64(v: u64) -> Result<i64, BuildError> {
+    i64::try_from(v).map_err(|_| BuildError::U64Overflow { value: v })
+}
+
+/// Errors if any `U64` is too large to bind as an `i64`.
+pub(crate) fn check_u64_range<'a>(
+    values: impl IntoIterator<Item = &'a SQLValue>,
+) -> Result<(), BuildError> {
+    for v in values {
+        if let SQLValue::U64(v) = v {
+            u64_to_i64(*v)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encodes the string as a json string literal.
+#[cfg(feature = "sqlite")]
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
         }
     }
+    out.push('"');
+    out
+}
+
+/// Wraps the string in single quotes, doubling any embedded single quotes.
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+// Smaller and unsigned types are widened to the nearest type Postgres can hold without
+// losing range, since it has no unsigned or single byte integers.
+impl From<i8> for SQLValue {
+    fn from(v: i8) -> Self {
+        SQLValue::I16(v.into())
+    }
+}
+
+impl From<u8> for SQLValue {
+    fn from(v: u8) -> Self {
+        SQLValue::I16(v.into())
+    }
+}
+
+impl From<u16> for SQLValue {
+    fn from(v: u16) -> Self {
+        SQLValue::I32(v.into())
+    }
+}
+
+impl From<u32> for SQLValue {
+    fn from(v: u32) -> Self {
+        SQLValue::I64(v.into())
+    }
+}
+
+impl From<f32> for SQLValue {
+    fn from(v: f32) -> Self {
+        SQLValue::F64(v.into())
+    }
 }
 
 impl From<i16> for SQLValue {
@@ -88,12 +368,42 @@ impl From<NaiveDateTime> for SQLValue {
     }
 }
 
+impl From<NaiveDate> for SQLValue {
+    fn from(v: NaiveDate) -> Self {
+        SQLValue::Date(v)
+    }
+}
+
+impl From<DateTime<Utc>> for SQLValue {
+    fn from(v: DateTime<Utc>) -> Self {
+        SQLValue::DateTimeTz(v)
+    }
+}
+
 impl From<Vec<i64>> for SQLValue {
     fn from(v: Vec<i64>) -> Self {
         SQLValue::VecI64(v)
     }
 }
 
+impl From<Vec<i32>> for SQLValue {
+    fn from(v: Vec<i32>) -> Self {
+        SQLValue::VecI32(v)
+    }
+}
+
+impl From<Vec<String>> for SQLValue {
+    fn from(v: Vec<String>) -> Self {
+        SQLValue::VecString(v)
+    }
+}
+
+impl From<Vec<u8>> for SQLValue {
+    fn from(v: Vec<u8>) -> Self {
+        SQLValue::Bytes(v)
+    }
+}
+
 impl From<u64> for SQLValue {
     fn from(v: u64) -> Self {
         SQLValue::U64(v)
@@ -112,8 +422,51 @@ impl From<String> for SQLValue {
     }
 }
 
+impl From<&str> for SQLValue {
+    fn from(v: &str) -> Self {
+        SQLValue::String(v.to_string())
+    }
+}
+
+impl From<&String> for SQLValue {
+    fn from(v: &String) -> Self {
+        SQLValue::String(v.clone())
+    }
+}
+
 impl From<bool> for SQLValue {
     fn from(v: bool) -> Self {
         SQLValue::Bool(v)
     }
 }
+
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for SQLValue {
+    fn from(v: uuid::Uuid) -> Self {
+        SQLValue::Uuid(v)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Decimal> for SQLValue {
+    fn from(v: rust_decimal::Decimal) -> Self {
+        SQLValue::Decimal(v)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Value> for SQLValue {
+    fn from(v: serde_json::Value) -> Self {
+        SQLValue::Json(v)
+    }
+}
+
+/// `Some(v)` converts the same as `v`, while `None` becomes [SQLValue::Null].
+impl<T: Into<SQLValue>> From<Option<T>> for SQLValue {
+    fn from(v: Option<T>) -> Self {
+        match v {
+            Some(v) => v.into(),
+            None => SQLValue::Null,
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::sql_value::SQLValue;
+
+/// The sampling method for a `tablesample` clause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SampleMethod {
+    /// Samples whole pages, fast but less random.
+    System,
+    /// Samples individual rows.
+    Bernoulli,
+}
+
+impl SampleMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SampleMethod::System => "system",
+            SampleMethod::Bernoulli => "bernoulli",
+        }
+    }
+}
+
+/// A `tablesample method (percent)` clause, with an optional `repeatable (seed)`.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableSample {
+    method: SampleMethod,
+    percent: f64,
+    seed: Option<i64>,
+}
+
+impl TableSample {
+    pub fn new(method: SampleMethod, percent: f64, seed: Option<i64>) -> Self {
+        Self {
+            method,
+            percent,
+            seed,
+        }
+    }
+
+    pub fn param_count(&self) -> usize {
+        1 + usize::from(self.seed.is_some())
+    }
+
+    pub fn parts(self) -> (String, Vec<SQLValue>) {
+        // Postgres only implicitly casts the percent to `real`, which a bound `float8` isn't
+        let mut out = format!(" tablesample {} (?::real)", self.method.as_str());
+        let mut values = vec![SQLValue::F64(self.percent)];
+
+        if let Some(seed) = self.seed {
+            out.push_str(" repeatable (?)");
+            values.push(seed.into());
+        }
+
+        (out, values)
+    }
+}
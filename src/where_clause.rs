@@ -1,59 +1,159 @@
+use std::borrow::Cow;
+
 use crate::sql_value::SQLValue;
 use crate::BoolKind;
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum Clause {
+    Simple(Cow<'static, str>, Vec<SQLValue>),
+    /// A nested set of clauses, wrapped in parentheses when rendered.
+    Group(WhereClauses),
+}
+
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WhereClauses {
-    clauses: Vec<(String, SQLValue, BoolKind)>,
-    multi_clauses: Vec<(String, Vec<SQLValue>)>,
+    /// Every clause in the order it was added, along with the operator joining it to the
+    /// clause before it.
+    clauses: Vec<(Clause, BoolKind)>,
 }
 
 impl WhereClauses {
     pub fn new() -> Self {
+        Self { clauses: vec![] }
+    }
+
+    /// Pre-allocates room for `clauses` clauses.
+    pub fn with_capacity(clauses: usize) -> Self {
         Self {
-            clauses: vec![],
-            multi_clauses: vec![],
+            clauses: Vec::with_capacity(clauses),
         }
     }
 
-    pub fn push(&mut self, clause: impl Into<String>, value: impl Into<SQLValue>, kind: BoolKind) {
-        self.clauses.push((clause.into(), value.into(), kind));
+    pub fn push(
+        &mut self,
+        clause: impl Into<Cow<'static, str>>,
+        value: impl Into<SQLValue>,
+        kind: BoolKind,
+    ) {
+        self.clauses
+            .push((Clause::Simple(clause.into(), vec![value.into()]), kind));
     }
 
-    pub fn push_multi(&mut self, clause: impl Into<String>, value: Vec<SQLValue>) {
-        self.multi_clauses.push((clause.into(), value));
+    /// Pushes a clause that binds any number of values, one per `?` placeholder.
+    pub fn push_many(
+        &mut self,
+        clause: impl Into<Cow<'static, str>>,
+        values: Vec<SQLValue>,
+        kind: BoolKind,
+    ) {
+        self.clauses
+            .push((Clause::Simple(clause.into(), values), kind));
+    }
+
+    /// Pushes a nested group of clauses. Empty groups are ignored.
+    pub fn push_group(&mut self, group: WhereClauses, kind: BoolKind) {
+        if group.is_empty() {
+            return;
+        }
+
+        self.clauses.push((Clause::Group(group), kind));
+    }
+
+    /// Combines two sets of clauses with `and`. Each non-empty side is wrapped in
+    /// parentheses, so an `or` on one side can't leak into the other.
+    pub fn and(self, other: WhereClauses) -> WhereClauses {
+        if other.is_empty() {
+            return self;
+        }
+        if self.is_empty() {
+            return other;
+        }
+
+        let mut out = WhereClauses::with_capacity(2);
+        out.push_group(self, BoolKind::And);
+        out.push_group(other, BoolKind::And);
+        out
+    }
+
+    /// The number of top level clauses. A nested group counts as one.
+    pub fn len(&self) -> usize {
+        self.clauses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clauses.is_empty()
+    }
+
+    /// The number of values that will be bound, including those in nested groups.
+    pub fn param_count(&self) -> usize {
+        self.clauses
+            .iter()
+            .map(|(clause, _)| match clause {
+                Clause::Simple(_, v) => v.iter().filter(|v| v.is_bound()).count(),
+                Clause::Group(group) => group.param_count(),
+            })
+            .sum()
+    }
+
+    /// A rough estimate of the rendered length, used to pre-size the output.
+    pub fn len_hint(&self) -> usize {
+        self.clauses
+            .iter()
+            .map(|(clause, _)| match clause {
+                Clause::Simple(s, _) => s.len() + 5,
+                Clause::Group(group) => group.len_hint() + 7,
+            })
+            .sum()
     }
 
     pub fn parts(self) -> (String, Vec<SQLValue>) {
-        if self.clauses.is_empty() && self.multi_clauses.is_empty() {
+        self.parts_with_keyword("where")
+    }
+
+    /// Builds the clauses prefixed by the given keyword, ie `where` or `having`.
+    pub fn parts_with_keyword(self, keyword: &str) -> (String, Vec<SQLValue>) {
+        if self.is_empty() {
             return ("".to_string(), vec![]);
         }
 
-        // Build up where clauses
-        let mut out = " where ".to_string();
+        let mut out = String::with_capacity(keyword.len() + 2 + self.len_hint());
+        out.push(' ');
+        out.push_str(keyword);
+        out.push(' ');
 
-        for (i, (s, _, kind)) in self.clauses.iter().enumerate() {
-            out.push_str(s.as_str());
-            if i != self.clauses.len() - 1 {
+        let values = self.render_into(&mut out);
+
+        (out, values)
+    }
+
+    /// Writes the clauses joined by their boolean operators to `out`, returning their values.
+    /// Each clause's kind is the operator joining it to the clause before it, so the first
+    /// clause's kind is ignored.
+    fn render_into(self, out: &mut String) -> Vec<SQLValue> {
+        let mut values = Vec::with_capacity(self.param_count());
+
+        for (i, (clause, kind)) in self.clauses.into_iter().enumerate() {
+            if i != 0 {
                 out.push(' ');
                 out.push_str(kind.as_str());
                 out.push(' ');
             }
-        }
 
-        for (i, (s, _)) in self.multi_clauses.iter().enumerate() {
-            out.push_str(s.as_str());
-            if i != self.multi_clauses.len() - 1 {
-                out.push_str(" and ");
+            match clause {
+                Clause::Simple(s, v) => {
+                    out.push_str(&s);
+                    values.extend(v);
+                }
+                Clause::Group(group) => {
+                    out.push('(');
+                    values.extend(group.render_into(out));
+                    out.push(')');
+                }
             }
         }
 
-        (
-            out,
-            self.clauses
-                .into_iter()
-                .map(|(_, v, _)| v)
-                .chain(self.multi_clauses.into_iter().flat_map(|(_, v)| v))
-                .collect(),
-        )
+        values
     }
 }